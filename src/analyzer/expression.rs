@@ -1,171 +1,469 @@
-use super::{syntax::identifier, SyntaxError};
+use super::{
+    structure::{qualified, terminated},
+    SyntaxError,
+};
 use crate::{
-    analyzer::syntax::{binary_operator, literal, symbol, unary_operator},
-    ast::{Expression, Operator, Value},
-    Token, TokenStream,
+    analyzer::syntax::{
+        datatype, infix_operator, literal, postfix_operator, prefix_operator, symbol,
+    },
+    ast::{Datatype, Expression, Infix, Lvalue, Postfix, Prefix, Sizeof, Value},
+    tokenizer::unescape,
+    Literal, Span, Token, TokenStream,
 };
-use std::iter::Peekable;
+use std::iter::{once, Once, Peekable};
+
+/// Expression along with the token it starts at
+type Term<'a> = (Expression<'a>, Token<'a>);
 
 impl<'a> Expression<'a> {
     pub fn from_stream(
         stream: &mut Peekable<impl TokenStream<'a>>,
         terminators: Vec<&str>,
     ) -> Result<(Self, Token<'a>), SyntaxError<'a>> {
-        let expression_error = SyntaxError {
-            expected: "expression".to_owned(),
-            found: stream.peek().map(|&x| x),
-        };
+        let term = operand(stream)?;
+        Self::terminate(stream, term, terminators)
+    }
 
-        let mut output = Vec::new();
-        let mut stack = Vec::new();
-        let mut complete = false;
-
-        let mut apply = |mutation: Mutation<'a>| -> Result<(), SyntaxError<'a>> {
-            let expression = match mutation {
-                Mutation::Expression(expression) => Some(expression),
-                Mutation::Operator(Some(operator)) => match operator {
-                    Operator::Group => None,
-                    Operator::Unary(op) => match output.pop() {
-                        Some(lhs) => Some(Expression::Unary {
-                            lhs: Box::new(lhs),
-                            op,
-                        }),
-                        None => return Err(expression_error.clone()),
-                    },
-                    Operator::Binary(op) => match (output.pop(), output.pop()) {
-                        (Some(rhs), Some(lhs)) => Some(Expression::Binary {
-                            lhs: Box::new(lhs),
-                            rhs: Box::new(rhs),
-                            op,
-                        }),
-                        _ => return Err(expression_error.clone()),
-                    },
-                },
-                _ => None,
-            };
+    /// Continues parsing an expression whose leading identifier was already consumed
+    pub fn from_identifier(
+        stream: &mut Peekable<impl TokenStream<'a>>,
+        identifier: &'a str,
+        span: Span,
+        terminators: Vec<&str>,
+    ) -> Result<(Self, Token<'a>), SyntaxError<'a>> {
+        let term = Expression::Value(Value::Pointer(Lvalue::Variable(identifier, span)), span);
+        Self::terminate(
+            stream,
+            (term, Token::Identifier(identifier, span)),
+            terminators,
+        )
+    }
 
-            if let Some(expression) = expression {
-                output.push(expression);
-            }
+    /// Whether the token can start an expression
+    pub fn begins(token: Token<'a>) -> bool {
+        matches!(
+            token,
+            Token::Data(..)
+                | Token::Identifier(..)
+                | Token::Symbol("(", _)
+                | Token::Keyword("sizeof", _)
+        ) || lookup(token, prefix_operator).is_some()
+    }
 
-            Ok(())
+    fn terminate(
+        stream: &mut Peekable<impl TokenStream<'a>>,
+        term: Term<'a>,
+        terminators: Vec<&str>,
+    ) -> Result<(Self, Token<'a>), SyntaxError<'a>> {
+        // Inside of lists commas separate the elements instead of being operators
+        let power = match terminators.contains(&",") {
+            true => binding(Infix::Comma.precedence(), false).1,
+            false => 0,
         };
+        let (expression, _) = operators(stream, term, power)?;
+        Ok((expression, terminated(stream, terminators)?))
+    }
+}
 
-        let terminator = loop {
-            match term(stream, &mut stack, complete) {
-                Ok((mutations, completed)) => {
-                    complete = completed;
-                    for mutation in mutations {
-                        apply(mutation)?;
-                    }
+/// Converts a precedence level into left and right binding powers,
+/// the lower the level is the tighter the operator binds
+fn binding(precedence: i32, right_associative: bool) -> (i32, i32) {
+    let power = (16 - precedence) * 2;
+    match right_associative {
+        true => (power + 1, power),
+        false => (power, power + 1),
+    }
+}
+
+/// Looks the token up in an operator table without consuming it
+fn lookup<'a, T>(
+    token: Token<'a>,
+    table: fn(&mut Peekable<Once<Token<'a>>>) -> Result<T, SyntaxError<'a>>,
+) -> Option<T> {
+    table(&mut once(token).peekable()).ok()
+}
+
+fn expression<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+    power: i32,
+) -> Result<Term<'a>, SyntaxError<'a>> {
+    let term = operand(stream)?;
+    operators(stream, term, power)
+}
+
+fn operand<'a>(stream: &mut Peekable<impl TokenStream<'a>>) -> Result<Term<'a>, SyntaxError<'a>> {
+    let Some(&token) = stream.peek() else {
+        return Err(SyntaxError {
+            expected: "expression".to_owned(),
+            found: None,
+        });
+    };
+
+    if let Some(op) = lookup(token, prefix_operator) {
+        stream.next();
+        let power = binding(op.precedence(), true).1;
+        let expression = match op {
+            Prefix::Unary(op) => {
+                let (lhs, _) = expression(stream, power)?;
+                Expression::Unary {
+                    op,
+                    span: token.span().to(lhs.span()),
+                    lhs: Box::new(lhs),
                 }
-                Err(error)
-                    if terminators
-                        .iter()
-                        .any(|x| Some(Token::Symbol(x)) == error.found) =>
-                {
-                    break stream.next().unwrap();
+            }
+            Prefix::Update(op) => {
+                let (lhs, first) = expression(stream, power)?;
+                Expression::Update {
+                    op,
+                    span: token.span().to(lhs.span()),
+                    target: assignable(lhs, first)?,
+                    postfix: false,
                 }
-                Err(error) => return Err(error),
+            }
+            Prefix::Dereference | Prefix::Reference => {
+                return Err(SyntaxError {
+                    expected: "expression because pointers are not supported".to_owned(),
+                    found: Some(token),
+                })
             }
         };
 
-        while let Some(&Operator::Binary(_) | &Operator::Unary(_)) = stack.last() {
-            apply(Mutation::Operator(stack.pop()))?;
-        }
+        return Ok((expression, token));
+    }
 
-        match (stack.len(), output.len()) {
-            (0, 1) => Ok((output.pop().unwrap(), terminator)),
-            _ => Err(expression_error),
+    // Casts and `sizeof` bind like the other prefix operators
+    let power = binding(Prefix::Reference.precedence(), true).1;
+    let expression = match token {
+        Token::Data(Literal::String, ..) => string(stream)?,
+        Token::Data(..) => Expression::Value(literal(stream)?, token.span()),
+        Token::Identifier(name, span) => {
+            stream.next();
+            Expression::Value(Value::Pointer(Lvalue::Variable(name, span)), span)
         }
-    }
+        Token::Symbol("(", _) => {
+            stream.next();
+            match typename(stream)? {
+                Some((datatype, _)) => {
+                    let (value, _) = expression(stream, power)?;
+                    Expression::Cast {
+                        datatype,
+                        span: token.span().to(value.span()),
+                        value: Box::new(value),
+                    }
+                }
+                None => {
+                    let (expression, _) = expression(stream, 0)?;
+                    symbol(stream, ")")?;
+                    expression
+                }
+            }
+        }
+        Token::Keyword("sizeof", _) => {
+            stream.next();
+            let (operand, end) = match stream.peek().copied() {
+                Some(open @ Token::Symbol("(", _)) => {
+                    stream.next();
+                    match typename(stream)? {
+                        Some((datatype, end)) => (Sizeof::Type(datatype), end),
+                        None => {
+                            let (expression, _) = expression(stream, 0)?;
+                            let close = symbol(stream, ")")?;
+                            let (expression, _) = operators(stream, (expression, open), power)?;
+                            let end = close.span().to(expression.span());
+                            (Sizeof::Expression(Box::new(expression)), end)
+                        }
+                    }
+                }
+                _ => {
+                    let (expression, _) = expression(stream, power)?;
+                    let end = expression.span();
+                    (Sizeof::Expression(Box::new(expression)), end)
+                }
+            };
+            Expression::Sizeof(operand, token.span().to(end))
+        }
+        _ => {
+            return Err(SyntaxError {
+                expected: "expression".to_owned(),
+                found: Some(token),
+            })
+        }
+    };
+
+    Ok((expression, token))
 }
 
-fn term<'a>(
+fn operators<'a>(
     stream: &mut Peekable<impl TokenStream<'a>>,
-    stack: &mut Vec<Operator>,
-    complete: bool,
-) -> Result<(Vec<Mutation<'a>>, bool), SyntaxError<'a>> {
-    let mut mutations = Vec::new();
-    let mut completed = false;
-
-    literal(stream)
-        .and_then(|x| {
-            completed = true;
-            mutations.push(Mutation::Expression(Expression::Value(x)));
-            Ok(())
-        })
-        .or_else(|_: SyntaxError<'a>| {
-            let (identifier, index) = identifier(stream)?;
-            completed = true;
-
-            let value = Expression::Value(Value::Pointer(identifier, index));
-
-            mutations.push(Mutation::Expression(value));
-            Ok(())
-        })
-        .or_else(|_: SyntaxError<'a>| {
-            if !complete && let Ok(op) = unary_operator(stream) {
-                stack.push(Operator::Unary(op));
-                return Ok(());
+    (mut lhs, first): Term<'a>,
+    power: i32,
+) -> Result<Term<'a>, SyntaxError<'a>> {
+    while let Some(&token) = stream.peek() {
+        if let Some(op) = lookup(token, postfix_operator) {
+            if binding(op.precedence(), false).0 < power {
+                break;
             }
 
-            let op = binary_operator(stream)?;
-            let mut top = stack.last();
-            completed = false;
+            let unsupported = |reason: &str| SyntaxError {
+                expected: format!("operator because {}", reason),
+                found: Some(token),
+            };
+
+            stream.next();
+            lhs = match op {
+                Postfix::Update(op) => Expression::Update {
+                    op,
+                    span: lhs.span().to(token.span()),
+                    target: assignable(lhs, first)?,
+                    postfix: true,
+                },
+                Postfix::Index => match lhs {
+                    Expression::Value(Value::Pointer(Lvalue::Variable(name, span)), _) => {
+                        let (index, _) = expression(stream, 0)?;
+                        let end = symbol(stream, "]")?;
+                        let element = Lvalue::Element(name, Box::new(index), span);
+                        Expression::Value(Value::Pointer(element), span.to(end.span()))
+                    }
+                    _ => return Err(unsupported("only array variables can be indexed")),
+                },
+                Postfix::Call => return Err(unsupported("function calls are not supported")),
+                Postfix::Member => return Err(unsupported("structures are not supported")),
+            };
+            continue;
+        }
+
+        let Some(op) = lookup(token, infix_operator) else {
+            break;
+        };
+        let (left, right) = binding(op.precedence(), op.right_associative());
+        if left < power {
+            break;
+        }
 
-            while let Some(&operator) = top
-                && match operator {
-                    Operator::Binary(x) if x.precedence() <= op.precedence() => true,
-                    Operator::Unary(_) => true,
-                    _ => false,
+        stream.next();
+        lhs = match op {
+            Infix::Binary(op) => {
+                let (rhs, _) = expression(stream, right)?;
+                Expression::Binary {
+                    op,
+                    span: lhs.span().to(rhs.span()),
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
                 }
-            {
-                mutations.push(Mutation::Operator(stack.pop()));
-                top = stack.last();
             }
+            Infix::Assignment(op) => {
+                let target = assignable(lhs, first)?;
+                let (value, _) = expression(stream, right)?;
+                Expression::Assignment {
+                    op,
+                    span: target.span().to(value.span()),
+                    target,
+                    value: Box::new(value),
+                }
+            }
+            Infix::Conditional => {
+                let (then, _) = expression(stream, 0)?;
+                symbol(stream, ":").map_err(|error| SyntaxError {
+                    expected: "':' of the conditional expression".to_owned(),
+                    ..error
+                })?;
+                let (otherwise, _) = expression(stream, right)?;
 
-            stack.push(Operator::Binary(op));
-            Ok(())
-        })
-        .or_else(|_: SyntaxError<'a>| {
-            symbol(stream, "(")?;
-            stack.push(Operator::Group);
-            completed = false;
-            Ok(())
-        })
-        .or_else(|_: SyntaxError<'a>| {
-            let token = stream.peek().map(|&x| x);
-            if token == Some(Token::Symbol(")")) && !stack.contains(&Operator::Group) {
-                return Err(SyntaxError {
-                    expected: "expression term".to_owned(),
-                    found: token,
-                });
+                Expression::Conditional {
+                    span: lhs.span().to(otherwise.span()),
+                    condition: Box::new(lhs),
+                    then: Box::new(then),
+                    otherwise: Box::new(otherwise),
+                }
+            }
+            Infix::Comma => {
+                let (rhs, _) = expression(stream, right)?;
+                Expression::Comma {
+                    span: lhs.span().to(rhs.span()),
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                }
             }
+        };
+    }
 
-            symbol(stream, ")")?;
-            let mut top = stack.last();
-            completed = true;
+    Ok((lhs, first))
+}
 
-            while let Some(&Operator::Binary(_) | &Operator::Unary(_)) = top {
-                mutations.push(Mutation::Operator(stack.pop()));
-                top = stack.last();
-            }
+/// Adjacent string literals are concatenated into one
+fn string<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+) -> Result<Expression<'a>, SyntaxError<'a>> {
+    let mut bytes = Vec::new();
+    let mut span: Option<Span> = None;
+    while let Some(&token @ Token::Data(Literal::String, text, _)) = stream.peek() {
+        let Some(unescaped) = unescape(text) else {
+            return Err(SyntaxError {
+                expected: "string with valid escape sequences".to_owned(),
+                found: Some(token),
+            });
+        };
 
-            stack.pop();
-            Ok(())
-        })
-        .or_else(|error: SyntaxError<'a>| {
-            Err(SyntaxError {
-                expected: "expression term".to_owned(),
-                found: error.found,
-            })
-        })?;
+        bytes.extend(unescaped);
+        span = Some(span.map_or(token.span(), |x| x.to(token.span())));
+        stream.next();
+    }
+
+    Ok(Expression::Value(
+        Value::String(bytes),
+        span.unwrap_or_default(),
+    ))
+}
+
+/// Parenthesized type name of a cast or `sizeof` whose `(` was already consumed,
+/// along with the span of its `)`
+fn typename<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+) -> Result<Option<(Datatype<'a>, Span)>, SyntaxError<'a>> {
+    match stream.peek().copied() {
+        Some(Token::Identifier(..)) | None => return Ok(None),
+        Some(Token::Keyword("const" | "volatile", _)) => (),
+        Some(token) if lookup(token, datatype).is_none() => return Ok(None),
+        _ => (),
+    }
+
+    let datatype = qualified(stream)?;
+    let end = symbol(stream, ")")?;
+    Ok(Some((datatype, end.span())))
+}
 
-    Ok((mutations, completed))
+fn assignable<'a>(
+    expression: Expression<'a>,
+    first: Token<'a>,
+) -> Result<Lvalue<'a>, SyntaxError<'a>> {
+    match expression {
+        Expression::Value(Value::Pointer(lvalue), _) => Ok(lvalue),
+        _ => Err(SyntaxError {
+            expected: "assignable expression".to_owned(),
+            found: Some(first),
+        }),
+    }
 }
 
-enum Mutation<'a> {
-    Expression(Expression<'a>),
-    Operator(Option<Operator>),
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{Datatype, Expression, Lvalue, Sizeof, Statement, Value},
+        testing::parse,
+    };
+
+    /// Tree of the expression in prefix notation, such as `(Addition a b)`
+    fn shape(expression: &Expression) -> String {
+        let lvalue = |target: &Lvalue| match target {
+            Lvalue::Variable(name, _) => name.to_string(),
+            Lvalue::Element(name, index, _) => format!("{name}[{}]", shape(index)),
+        };
+        let datatype = |datatype: &Datatype| match datatype {
+            Datatype::Type(compound, _) => format!("{:?}", compound.0),
+            Datatype::Alias(name, ..) => name.to_string(),
+        };
+        match expression {
+            Expression::Value(Value::Data(data), _) => i64::from(data).to_string(),
+            Expression::Value(Value::Pointer(target), _) => lvalue(target),
+            Expression::Value(Value::String(_), _) => "string".to_owned(),
+            Expression::Binary { op, lhs, rhs, .. } => {
+                format!("({op:?} {} {})", shape(lhs), shape(rhs))
+            }
+            Expression::Unary { op, lhs, .. } => format!("({op:?} {})", shape(lhs)),
+            Expression::Assignment {
+                op, target, value, ..
+            } => match op {
+                Some(op) => format!("({op:?}= {} {})", lvalue(target), shape(value)),
+                None => format!("(= {} {})", lvalue(target), shape(value)),
+            },
+            Expression::Update {
+                op,
+                target,
+                postfix,
+                ..
+            } => match postfix {
+                true => format!("(Postfix{op:?} {})", lvalue(target)),
+                false => format!("(Prefix{op:?} {})", lvalue(target)),
+            },
+            Expression::Conditional {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => format!(
+                "(? {} {} {})",
+                shape(condition),
+                shape(then),
+                shape(otherwise)
+            ),
+            Expression::Comma { lhs, rhs, .. } => format!("(, {} {})", shape(lhs), shape(rhs)),
+            Expression::Cast {
+                datatype: x, value, ..
+            } => format!("(Cast {} {})", datatype(x), shape(value)),
+            Expression::Sizeof(Sizeof::Type(x), _) => format!("(Sizeof {})", datatype(x)),
+            Expression::Sizeof(Sizeof::Expression(value), _) => {
+                format!("(Sizeof {})", shape(value))
+            }
+        }
+    }
+
+    /// Each level of the binding powers, from the operands outwards
+    #[test]
+    fn precedence() {
+        let cases = [
+            // Postfix operators bind tighter than the prefix ones
+            ("-a++", "(Negation (PostfixIncrement a))"),
+            (
+                "++a + b--",
+                "(Addition (PrefixIncrement a) (PostfixDecrement b))",
+            ),
+            ("!a[i + 1]", "(Inversion a[(Addition i 1)])"),
+            ("-a * b", "(Multiplication (Negation a) b)"),
+            // Casts bind like the prefix operators, parenthesized values are not casts
+            ("(int) a + b", "(Addition (Cast Int a) b)"),
+            ("(T) -a", "(Cast T (Negation a))"),
+            ("(a) + b", "(Addition a b)"),
+            ("(a + b) * c", "(Multiplication (Addition a b) c)"),
+            // `sizeof` takes its operand before the binary operators do
+            ("sizeof a + b", "(Addition (Sizeof a) b)"),
+            ("sizeof (int) * 2", "(Multiplication (Sizeof Int) 2)"),
+            ("sizeof -a", "(Sizeof (Negation a))"),
+            // Binary operators from the tightest to the loosest, each left-associative
+            ("a - b - c", "(Subtraction (Subtraction a b) c)"),
+            (
+                "a + b * c % d",
+                "(Addition a (Remainder (Multiplication b c) d))",
+            ),
+            ("a << b + c", "(LeftShift a (Addition b c))"),
+            ("a < b << c", "(Less a (LeftShift b c))"),
+            ("a == b < c", "(Equal a (Less b c))"),
+            ("a & b == c", "(BitwiseAnd a (Equal b c))"),
+            ("a ^ b & c", "(BitwiseXor a (BitwiseAnd b c))"),
+            ("a | b ^ c", "(BitwiseOr a (BitwiseXor b c))"),
+            ("a && b | c", "(And a (BitwiseOr b c))"),
+            ("a || b && c", "(Or a (And b c))"),
+            // Conditionals and assignments are right-associative
+            ("a || b ? c : d", "(? (Or a b) c d)"),
+            ("a ? b : c ? d : e", "(? a b (? c d e))"),
+            ("a ? b ? c : d : e", "(? a (? b c d) e)"),
+            ("a = b ? c : d", "(= a (? b c d))"),
+            ("a = b = c", "(= a (= b c))"),
+            ("a += b -= c", "(Addition= a (Subtraction= b c))"),
+            // Commas are the loosest of all and left-associative
+            ("a, b = c, d", "(, (, a (= b c)) d)"),
+            ("a = (b, c)", "(= a (, b c))"),
+        ];
+        for (text, expected) in cases {
+            let text = format!("typedef int T; int main() {{ return {text}; }}");
+            let (ast, errors) = parse(&text);
+            assert!(errors.is_empty(), "{text}: {errors:?}");
+            let Some(Statement::Function(function, _)) = ast.get(1) else {
+                panic!("{text}: {ast:?}");
+            };
+            let Some(Statement::Return(expression, _)) = function.body.first() else {
+                panic!("{text}: {function:?}");
+            };
+            assert_eq!(shape(expression), expected, "{text}");
+        }
+    }
 }
@@ -1,19 +1,119 @@
 mod expression;
 mod structure;
 mod syntax;
+mod typename;
 
 use crate::ast::Statement;
 use crate::error::syntax::SyntaxError;
 use crate::TokenStream;
 use structure::block;
+use typename::Typenames;
 
 pub trait Analyzable<'a> {
-    fn analyze(self) -> Result<Vec<Statement<'a>>, SyntaxError<'a>>;
+    /// Parses the partial AST, where broken statements are replaced with errors,
+    /// along with all the syntax errors in order of appearance
+    fn analyze(self) -> (Vec<Statement<'a>>, Vec<SyntaxError<'a>>);
 }
 
 impl<'a, T: TokenStream<'a> + 'a> Analyzable<'a> for T {
-    fn analyze(self) -> Result<Vec<Statement<'a>>, SyntaxError<'a>> {
-        let mut stream = self.peekable();
-        block(&mut stream, "")
+    fn analyze(self) -> (Vec<Statement<'a>>, Vec<SyntaxError<'a>>) {
+        let mut stream = Typenames::new(self).peekable();
+        let ast = block(&mut stream, "");
+
+        let mut errors = Vec::new();
+        collect_errors(&ast, &mut errors);
+        (ast, errors)
+    }
+}
+
+fn collect_errors<'a>(block: &[Statement<'a>], errors: &mut Vec<SyntaxError<'a>>) {
+    for statement in block {
+        match statement {
+            Statement::Error(error, _) => errors.push(error.clone()),
+            Statement::Function(function, _) => collect_errors(&function.body, errors),
+            Statement::Loop(repetition, _) => collect_errors(&repetition.body, errors),
+            Statement::Switch(selection, _) => collect_errors(&selection.body, errors),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{Expression, Extent, Loop, Statement},
+        testing::parse,
+    };
+
+    /// Each mistake is reported once, the recovery does not take what follows it for new errors
+    #[test]
+    fn recovery() {
+        let cases = [
+            ("int f(int x)", 1),
+            ("int f(int x) { return x; } int main() { return 0; }", 1),
+            ("int main() { f(int x); return 0; }", 1),
+            ("int main() { for (int i = ; i < 3; i++) {} return 0; }", 1),
+            ("int main() { for (return 0; x < 3; x++) {} return 0; }", 1),
+            ("int main() { for (int i = 0; i < 3; i++ {} return 0; }", 1),
+            (
+                "int main() { switch (1 +) { case 1: break; } return 0; }",
+                1,
+            ),
+            ("int main() { int x = 5 return x; }", 1),
+            ("int main() { int = 5; int y = ; return 0; }", 2),
+            ("int main() { x = (1 + ; y = 2; return 0; }", 1),
+            ("int x = 1; int main() { return x; }", 0),
+        ];
+        for (text, count) in cases {
+            let (_, errors) = parse(text);
+            let messages: Vec<_> = errors.iter().map(|x| x.expected.clone()).collect();
+            assert_eq!(errors.len(), count, "{text:?}: {messages:?}");
+        }
+    }
+
+    /// Loops of the function in the text, which must not have any syntax errors
+    fn loops<'a>(ast: &'a [Statement<'a>]) -> Vec<&'a Loop<'a>> {
+        let Some(Statement::Function(function, _)) = ast.first() else {
+            panic!("{ast:?}");
+        };
+        function
+            .body
+            .iter()
+            .filter_map(|x| match x {
+                Statement::Loop(repetition, _) => Some(repetition),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The first clause of a loop either declares its variables or is an expression
+    #[test]
+    fn loop_clauses() {
+        let text = "int main() { int n = 3; for (int i = 0, j[2], k = n; i < k; i++, k--) {} for (n = 0, n = 1; n; n--) {} for (n; n; n--) {} return 0; }";
+        let (ast, errors) = parse(text);
+        assert!(errors.is_empty(), "{errors:?}");
+        let loops = loops(&ast);
+
+        let Statement::Variables(variables, _) = &*loops[0].initialization else {
+            panic!("{:?}", loops[0]);
+        };
+        let names: Vec<_> = variables.iter().map(|x| x.name).collect();
+        assert_eq!(names, ["i", "j", "k"]);
+        assert!(matches!(variables[1].extent, Some(Extent::Size(_))));
+        assert!(variables[2].assignment.is_some());
+        assert!(matches!(loops[0].increment, Expression::Comma { .. }));
+
+        let Statement::Expression(Expression::Comma { lhs, rhs, .. }, _) =
+            &*loops[1].initialization
+        else {
+            panic!("{:?}", loops[1]);
+        };
+        assert!(matches!(**lhs, Expression::Assignment { .. }));
+        assert!(matches!(**rhs, Expression::Assignment { .. }));
+
+        assert!(matches!(
+            &*loops[2].initialization,
+            Statement::Expression(Expression::Value(..), _)
+        ));
     }
 }
@@ -1,119 +1,208 @@
 use super::{syntax::*, SyntaxError};
-use crate::{ast::*, Token, TokenStream};
+use crate::{ast::*, Span, Token, TokenStream};
 use std::iter::Peekable;
 
+/// Declaration whose type is already consumed, the qualifiers and the storage class
+/// can still follow it, such as in `int const x;`
 pub fn declaration<'a>(
     stream: &mut Peekable<impl TokenStream<'a>>,
-    mut datatype: Datatype<'a>,
+    start: Span,
+    datatype: Datatype<'a>,
+    mut storage: Option<Storage>,
 ) -> Result<Statement<'a>, SyntaxError<'a>> {
-    match (&datatype, stream.peek()) {
-        (Datatype::Alias(identifier), Some(Token::Symbol("="))) => {
-            symbol(stream, "=")?;
-            return Ok(Statement::Assignment(assignment(
-                stream,
-                (identifier, 0),
-                ";",
-            )?));
+    let mut qualifiers = Qualifiers::default();
+    specifiers(stream, &mut qualifiers, Some(&mut storage))?;
+    let datatype = datatype.qualify(qualifiers);
+
+    let declarator = identifier(stream)?;
+    Ok(match symbol(stream, "(") {
+        Ok(_) => {
+            let (identifier, span, extent) = declarator;
+            dimensioned(datatype, &extent, identifier, span)?;
+            let (function, end) = function(stream, datatype, identifier, span)?;
+            Statement::Function(function, start.to(end))
         }
-        (Datatype::Alias(identifier), Some(Token::Symbol("["))) => {
-            let index = index(stream)?;
-            symbol(stream, "=")?;
-            return Ok(Statement::Assignment(assignment(
-                stream,
-                (identifier, index),
-                ";",
-            )?));
+        Err(_) => {
+            let (variables, end) = variables(stream, datatype, storage, declarator, ";")?;
+            Statement::Variables(variables, start.to(end))
         }
-        _ => {}
+    })
+}
+
+/// Rejects the extent of the declarator when its type cannot be made into an array,
+/// the size itself is evaluated by the checker
+fn dimensioned<'a>(
+    datatype: Datatype<'a>,
+    extent: &Option<Extent<'a>>,
+    name: &'a str,
+    span: Span,
+) -> Result<(), SyntaxError<'a>> {
+    match (extent, datatype) {
+        // FUTURE: support arrays of aliases
+        (Some(_), Datatype::Alias(..)) => Err(SyntaxError {
+            expected: "no index because arrays of aliases are not supported".to_owned(),
+            found: Some(Token::Identifier(name, span)),
+        }),
+        _ => Ok(()),
     }
+}
 
-    let (identifier, size) = identifier(stream)?;
-    if size != 0 {
-        match datatype {
-            Datatype::Type(value) => {
-                datatype = Datatype::Type(Compound(value.0, size));
-            }
-            _ => {
-                // FUTURE: support arrays of aliases
-                return Err(SyntaxError {
-                    expected: "no index because arrays of aliases are not supported".to_owned(),
-                    found: Some(Token::Identifier(identifier)),
-                });
-            }
-        }
+/// Declaration that starts with its qualifiers or its storage class, such as `static int x;`,
+/// whose first keyword is already consumed
+pub fn specified<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+    first: Token<'a>,
+) -> Result<Statement<'a>, SyntaxError<'a>> {
+    let mut qualifiers = Qualifiers::default();
+    let mut storage = None;
+    specify(first, &mut qualifiers, Some(&mut storage))?;
+    specifiers(stream, &mut qualifiers, Some(&mut storage))?;
+
+    let datatype = datatype(stream)?.qualify(qualifiers);
+    declaration(stream, first.span(), datatype, storage)
+}
+
+/// Type along with the qualifiers around it, such as the one of a `typedef` or a cast
+pub fn qualified<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+) -> Result<Datatype<'a>, SyntaxError<'a>> {
+    let mut qualifiers = Qualifiers::default();
+    specifiers(stream, &mut qualifiers, None)?;
+    let datatype = datatype(stream)?;
+    specifiers(stream, &mut qualifiers, None)?;
+    Ok(datatype.qualify(qualifiers))
+}
+
+/// Consumes the qualifiers, along with the storage classes where a declaration can have them
+fn specifiers<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+    qualifiers: &mut Qualifiers,
+    mut storage: Option<&mut Option<Storage>>,
+) -> Result<(), SyntaxError<'a>> {
+    let allowed = storage.is_some();
+    while let Some(token) = stream.next_if(|x| match x {
+        Token::Keyword("const" | "volatile", _) => true,
+        Token::Keyword("static" | "extern", _) => allowed,
+        _ => false,
+    }) {
+        specify(token, qualifiers, storage.as_deref_mut())?;
     }
+    Ok(())
+}
 
-    Ok(match symbol(stream, "(") {
-        Ok(_) => Statement::Function(function(stream, datatype, identifier)?),
-        Err(_) => Statement::Variable(variable(stream, datatype, identifier)?),
-    })
+/// Applies the qualifier or the storage class, a declaration can only have one storage class
+fn specify<'a>(
+    token: Token<'a>,
+    qualifiers: &mut Qualifiers,
+    storage: Option<&mut Option<Storage>>,
+) -> Result<(), SyntaxError<'a>> {
+    match (token, storage) {
+        (Token::Keyword("const", _), _) => qualifiers.constant = true,
+        (Token::Keyword("volatile", _), _) => qualifiers.volatile = true,
+        (Token::Keyword(class, _), Some(storage)) if storage.is_none() => {
+            *storage = Some(match class {
+                "static" => Storage::Static,
+                _ => Storage::Extern,
+            })
+        }
+        _ => {
+            return Err(SyntaxError {
+                expected: "a single storage class".to_owned(),
+                found: Some(token),
+            })
+        }
+    }
+    Ok(())
 }
 
+/// Parses the rest of the function, along with the span of its closing brace
 pub fn function<'a>(
     stream: &mut Peekable<impl TokenStream<'a>>,
     datatype: Datatype<'a>,
     name: &'a str,
-) -> Result<Function<'a>, SyntaxError<'a>> {
+    span: Span,
+) -> Result<(Function<'a>, Span), SyntaxError<'a>> {
     match symbol(stream, ")") {
         Ok(_) => (),
         Err(SyntaxError { expected, found }) => {
-            return Err(SyntaxError {
-                expected: format!("{} because function arguments are not supported", expected),
-                found,
-            })
+            return Err(close(
+                stream,
+                SyntaxError {
+                    expected: format!("{} because function arguments are not supported", expected),
+                    found,
+                },
+            ))
         }
     }
     symbol(stream, "{")?;
-    let body = block(stream, "}")?;
+    let body = block(stream, "}");
+    let end = symbol(stream, "}")?;
 
-    Ok(Function {
-        datatype,
-        name,
-        body,
-    })
+    Ok((
+        Function {
+            datatype,
+            name,
+            span,
+            body,
+        },
+        end.span(),
+    ))
 }
 
-pub fn variable<'a>(
+/// Parses the comma-separated variables whose first declarator is already consumed,
+/// along with the span of their terminator. Each of them has its own extent and value
+pub fn variables<'a>(
     stream: &mut Peekable<impl TokenStream<'a>>,
     datatype: Datatype<'a>,
-    name: &'a str,
-) -> Result<Variable<'a>, SyntaxError<'a>> {
-    let assignment = match symbol(stream, "=") {
-        Ok(_) => Some(assignment(stream, (name, 0), ";")?),
-        Err(_) => {
-            symbol(stream, ";")?;
-            None
-        }
-    };
+    storage: Option<Storage>,
+    (mut name, mut span, mut extent): (&'a str, Span, Option<Extent<'a>>),
+    terminator: &str,
+) -> Result<(Vec<Variable<'a>>, Span), SyntaxError<'a>> {
+    let mut variables = Vec::new();
+    loop {
+        dimensioned(datatype, &extent, name, span)?;
+        let (assignment, end) = match symbol(stream, "=") {
+            Ok(_) => {
+                let (assignment, end) = assignment(stream, vec![",", terminator])?;
+                (Some(assignment), end)
+            }
+            Err(_) => (None, terminated(stream, vec![",", terminator])?),
+        };
 
-    Ok(Variable {
-        datatype,
-        assignment,
-        name,
-    })
+        variables.push(Variable {
+            datatype,
+            extent,
+            storage,
+            assignment,
+            name,
+            span,
+        });
+        if let Token::Symbol(x, end) = end
+            && x == terminator
+        {
+            return Ok((variables, end));
+        }
+        (name, span, extent) = identifier(stream)?;
+    }
 }
 
 pub fn typedef<'a>(
     stream: &mut Peekable<impl TokenStream<'a>>,
-) -> Result<Type<'a>, SyntaxError<'a>> {
-    let datatype = datatype(stream)?;
-    let (name, size) = identifier(stream)?;
-
-    let datatype = match (size > 1, datatype) {
-        // FUTURE: support arrays of aliases
-        (true, Datatype::Alias(_)) => {
-            return Err(SyntaxError {
-                expected: "no index because arrays of aliases are not supported".to_owned(),
-                found: Some(Token::Identifier(name)),
-            })
-        }
-        (true, Datatype::Type(Compound(primitive, _))) => Datatype::Type(Compound(primitive, size)),
-        (false, datatype) => datatype,
-    };
+    start: Span,
+) -> Result<Statement<'a>, SyntaxError<'a>> {
+    let datatype = qualified(stream)?;
+    let (name, span, extent) = identifier(stream)?;
+    dimensioned(datatype, &extent, name, span)?;
 
-    symbol(stream, ";")?;
+    let end = symbol(stream, ";")?;
 
-    return Ok(Type { name, datatype });
+    let typedef = Type {
+        name,
+        span,
+        datatype,
+        extent,
+    };
+    Ok(Statement::Type(typedef, start.to(end.span())))
 }
 
 pub fn expression<'a>(
@@ -123,91 +212,291 @@ pub fn expression<'a>(
     Expression::from_stream(stream, terminators)
 }
 
+pub fn statement_expression<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+    identifier: &'a str,
+    span: Span,
+) -> Result<Statement<'a>, SyntaxError<'a>> {
+    let (expression, end) = Expression::from_identifier(stream, identifier, span, vec![";"])?;
+    Ok(Statement::Expression(expression, span.to(end.span())))
+}
+
+/// Parses the value assigned to the variable, along with the terminator after it
 pub fn assignment<'a>(
     stream: &mut Peekable<impl TokenStream<'a>>,
-    identifier: (&'a str, usize),
-    terminator: &str,
-) -> Result<Assignment<'a>, SyntaxError<'a>> {
-    if let Some(Token::Symbol("{")) = stream.peek() {
-        Ok(Assignment {
-            identifier,
-            value: Initializer::List(initializer(stream, terminator)?),
-        })
+    terminators: Vec<&str>,
+) -> Result<(Assignment<'a>, Token<'a>), SyntaxError<'a>> {
+    let (value, end) = if let Some(Token::Symbol("{", _)) = stream.peek() {
+        let (values, end) = initializer(stream, terminators)?;
+        (Initializer::List(values), end)
     } else {
-        Ok(Assignment {
-            identifier,
-            value: Initializer::Expression(expression(stream, vec![terminator])?.0),
-        })
-    }
+        let (value, end) = expression(stream, terminators)?;
+        (Initializer::Expression(value), end)
+    };
+
+    Ok((Assignment { value }, end))
 }
 
 pub fn repetition<'a>(
     stream: &mut Peekable<impl TokenStream<'a>>,
-) -> Result<Loop<'a>, SyntaxError<'a>> {
+    start: Span,
+) -> Result<Statement<'a>, SyntaxError<'a>> {
     symbol(stream, "(")?;
-
-    let datatype = datatype(stream)?;
-    let (name, _) = identifier(stream)?;
-    let initialization = variable(stream, datatype, name)?;
-
-    let condition = expression(stream, vec![";"])?.0;
-
-    let identifier = identifier(stream)?;
-    symbol(stream, "=")?;
-    let increment = assignment(stream, identifier, ")")?;
+    let header = |stream: &mut Peekable<_>| {
+        let initialization = clause(stream)?;
+        let condition = expression(stream, vec![";"])?.0;
+        let increment = expression(stream, vec![")"])?.0;
+        Ok((initialization, condition, increment))
+    };
+    let (initialization, condition, increment) = header(stream).map_err(|x| close(stream, x))?;
 
     symbol(stream, "{")?;
-    let body = block(stream, "}")?;
+    let body = block(stream, "}");
+    let end = symbol(stream, "}")?;
 
-    Ok(Loop {
-        initialization,
+    let repetition = Loop {
+        initialization: Box::new(initialization),
         condition,
         increment,
         body,
-    })
+    };
+    Ok(Statement::Loop(repetition, start.to(end.span())))
+}
+
+pub fn selection<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+    start: Span,
+) -> Result<Statement<'a>, SyntaxError<'a>> {
+    symbol(stream, "(")?;
+    let value = expression(stream, vec![")"])
+        .map_err(|x| close(stream, x))?
+        .0;
+
+    symbol(stream, "{")?;
+    let body = block(stream, "}");
+    let end = symbol(stream, "}")?;
+
+    Ok(Statement::Switch(
+        Switch { value, body },
+        start.to(end.span()),
+    ))
+}
+
+pub fn label<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+    start: Span,
+) -> Result<Statement<'a>, SyntaxError<'a>> {
+    let (value, end) = expression(stream, vec![":"])?;
+    Ok(Statement::Case(
+        Case { value: Some(value) },
+        start.to(end.span()),
+    ))
+}
+
+/// Parses statements up to the terminator, which is left in the stream.
+/// Broken statements are kept as errors, so the parsing continues after them
+/// Statement or expression statement, the ones starting with an identifier
+/// are disambiguated by `statement`
+fn unit<'a>(stream: &mut Peekable<impl TokenStream<'a>>) -> Result<Statement<'a>, SyntaxError<'a>> {
+    match stream.peek().copied() {
+        Some(token) if !matches!(token, Token::Identifier(..)) && Expression::begins(token) => {
+            let (expression, end) = expression(stream, vec![";"])?;
+            Ok(Statement::Expression(
+                expression,
+                token.span().to(end.span()),
+            ))
+        }
+        _ => statement(stream),
+    }
+}
+
+/// First clause of a `for` loop along with its `;`, which either declares the loop's variables
+/// or is an expression, such as `i = 0, j = n`. It can also be left empty
+fn clause<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+) -> Result<Statement<'a>, SyntaxError<'a>> {
+    let start = stream.peek().copied();
+    match unit(stream)? {
+        x @ (Statement::Variables(..) | Statement::Expression(..) | Statement::Noop(_)) => Ok(x),
+        _ => Err(SyntaxError {
+            expected: "declaration or expression".to_owned(),
+            found: start,
+        }),
+    }
 }
 
 pub fn block<'a>(
     stream: &mut Peekable<impl TokenStream<'a>>,
     terminator: &str,
-) -> Result<Vec<Statement<'a>>, SyntaxError<'a>> {
-    let expected = match terminator {
-        "" => None,
-        x => Some(Token::Symbol(x)),
+) -> Vec<Statement<'a>> {
+    let terminates = |token: Option<Token>| match token {
+        Some(Token::Symbol(x, _)) => x == terminator,
+        None => terminator.is_empty(),
+        _ => false,
     };
 
     let mut block = Vec::new();
     loop {
-        match statement(stream) {
+        let start = stream.peek().copied();
+        let result = unit(stream);
+
+        match result {
             Ok(decl) => block.push(decl),
-            Err(error) if error.found == expected && error.expected == "statement" => {
+            Err(error) if terminates(error.found) && error.expected == "statement" => {
                 break;
             }
-            Err(error) => return Err(error),
+            // An unterminated block is reported by its caller
+            Err(SyntaxError { found: None, .. }) if !terminator.is_empty() => break,
+            Err(error) => {
+                let Some(start) = start else { break };
+                // Skip the token the statement got stuck on, so the recovery always progresses
+                if error.found == Some(start) {
+                    stream.next();
+                }
+                // A parenthesis the statement got stuck on is skipped along with its contents
+                let consumed = stream.peek().copied() != error.found;
+                let parentheses = match error.found {
+                    Some(Token::Symbol("(", _)) if consumed => 1,
+                    _ => 0,
+                };
+                let end = synchronize(stream, parentheses)
+                    .or(error.found.map(|x| x.span()))
+                    .unwrap_or(start.span());
+                let span = start.span().to(end);
+                let eof = error.found.is_none();
+
+                block.push(Statement::Error(error, span));
+                if eof {
+                    break;
+                }
+            }
         }
     }
 
-    if terminator != "" {
-        symbol(stream, terminator)?;
+    block
+}
+
+/// Skips tokens up to the start of the next statement, which is after a `;`
+/// or a whole block, or right before a statement keyword or an unmatched `}`.
+/// Semicolons and keywords within the parentheses do not count, including the ones
+/// that are already open. Returns the span of the last skipped token
+fn synchronize<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+    mut parentheses: usize,
+) -> Option<Span> {
+    const KEYWORDS: [&str; 17] = [
+        "typedef", "int", "float", "short", "long", "char", "for", "return", "switch", "case",
+        "default", "break", "goto", "const", "volatile", "static", "extern",
+    ];
+
+    let mut depth = 0;
+    let mut end = None;
+    while let Some(&token) = stream.peek() {
+        let boundary = match token {
+            Token::Symbol("}", _) => depth == 0,
+            Token::Keyword(x, _) => depth == 0 && parentheses == 0 && KEYWORDS.contains(&x),
+            _ => false,
+        };
+        if boundary {
+            return end;
+        }
+
+        stream.next();
+        end = Some(token.span());
+        match token {
+            Token::Symbol(";", _) if depth == 0 && parentheses == 0 => return end,
+            Token::Symbol("(", _) => parentheses += 1,
+            Token::Symbol(")", _) => parentheses = parentheses.saturating_sub(1),
+            Token::Symbol("{", _) => depth += 1,
+            Token::Symbol("}", _) => {
+                depth -= 1;
+                if depth == 0 {
+                    return end;
+                }
+            }
+            _ => (),
+        }
     }
 
-    return Ok(block);
+    end
+}
+
+/// Skips the rest of the parenthesized header that failed to parse, up to its closing `)`,
+/// so that the recovery does not take its contents for statements.
+/// A brace ends the header as well, since its `)` is missing then
+fn close<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+    error: SyntaxError<'a>,
+) -> SyntaxError<'a> {
+    let consumed = stream.peek().copied() != error.found;
+    let mut depth = match error.found {
+        Some(Token::Symbol("(", _)) if consumed => 2,
+        Some(Token::Symbol(")", _)) if consumed => 0,
+        _ => 1,
+    };
+    while depth > 0
+        && let Some(token) = stream.next_if(|x| !matches!(x, Token::Symbol("{" | "}", _)))
+    {
+        match token {
+            Token::Symbol("(", _) => depth += 1,
+            Token::Symbol(")", _) => depth -= 1,
+            _ => (),
+        }
+    }
+    error
 }
 
+/// Parses the initialization list, along with the terminator after it.
+/// The list can be empty or end with a comma, and its values can be designated with `[index] = value`
 fn initializer<'a>(
     stream: &mut Peekable<impl TokenStream<'a>>,
-    terminator: &str,
-) -> Result<Vec<Expression<'a>>, SyntaxError<'a>> {
+    terminators: Vec<&str>,
+) -> Result<(Vec<Entry<'a>>, Token<'a>), SyntaxError<'a>> {
     symbol(stream, "{")?;
-    let mut elements = Vec::new();
+    let mut entries = Vec::new();
     loop {
-        let (expression, terminator) = expression(stream, vec![",", "}"])?;
-        elements.push(expression);
+        if stream
+            .next_if(|x| matches!(x, Token::Symbol("}", _)))
+            .is_some()
+        {
+            break;
+        }
+        let index = match stream.next_if(|x| matches!(x, Token::Symbol("[", _))) {
+            Some(_) => {
+                let (index, _) = expression(stream, vec!["]"])?;
+                symbol(stream, "=")?;
+                Some(index)
+            }
+            None => None,
+        };
+        let (value, terminator) = expression(stream, vec![",", "}"])?;
+        entries.push(Entry { index, value });
 
-        if terminator == Token::Symbol("}") {
+        if let Token::Symbol("}", _) = terminator {
             break;
         }
     }
-    symbol(stream, terminator)?;
-    Ok(elements)
+    let end = terminated(stream, terminators)?;
+    Ok((entries, end))
+}
+
+/// Consumes any one of the terminators
+pub fn terminated<'a>(
+    stream: &mut Peekable<impl TokenStream<'a>>,
+    terminators: Vec<&str>,
+) -> Result<Token<'a>, SyntaxError<'a>> {
+    match stream.peek().copied() {
+        Some(token @ Token::Symbol(x, _)) if terminators.contains(&x) => {
+            stream.next();
+            Ok(token)
+        }
+        token => Err(SyntaxError {
+            expected: terminators
+                .iter()
+                .map(|x| format!("symbol({:?})", x))
+                .collect::<Vec<_>>()
+                .join(" or "),
+            found: token,
+        }),
+    }
 }
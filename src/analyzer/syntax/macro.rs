@@ -38,6 +38,8 @@ macro_rules! syntax {
                 }
             };
 
+            // Renames the stream for the rules that name it, otherwise it is rebound as is
+            #[allow(clippy::redundant_locals)]
             let first_ident!($($stream_name,)? stream) = stream;
             nested!(first_ident!($($stream_name,)? stream), expected, $($($cases $(if $conds)?),+ => {
                 $result
@@ -2,83 +2,145 @@ mod r#macro;
 
 use super::SyntaxError;
 use crate::{ast::Primitive, *};
-use analyzer::structure::{declaration, expression, repetition, typedef};
-use ast::{BinaryOperator, Compound, Data, Datatype, Statement, UnaryOperator, Value};
+use analyzer::structure::{
+    declaration, expression, label, repetition, selection, specified, statement_expression, typedef,
+};
+use ast::{
+    BinaryOperator, Case, Compound, Data, Datatype, Extent, Infix, Postfix, Prefix, Qualifiers,
+    Statement, UnaryOperator, UpdateOperator, Value,
+};
 use std::iter::Peekable;
 
 // FUTURE: `syntax!` composition to define everything declaratively
 
 syntax!(
   statement() with stream -> Statement<'a>:
-    Token::Keyword("typedef") => Statement::Type(typedef(stream)?);
-    Token::Keyword("return") => Statement::Return(expression(stream, vec![";"])?.0);
-    Token::Keyword("int") => declaration(stream, Datatype::Type(Compound (Primitive::Int, 1)))?;
-    Token::Keyword("float") => declaration(stream, Datatype::Type(Compound (Primitive::Float, 1)))?;
-    Token::Keyword("short") => declaration(stream, Datatype::Type(Compound (Primitive::Short, 1)))?;
-    Token::Keyword("long") => declaration(stream, Datatype::Type(Compound (Primitive::Long, 1)))?;
-    Token::Keyword("char") => declaration(stream, Datatype::Type(Compound (Primitive::Byte, 1)))?;
-    Token::Identifier(identifier) => declaration(stream, Datatype::Alias(identifier))?;
-    Token::Keyword("for") => Statement::Loop(repetition(stream)?);
-    Token::Symbol(";") => Statement::Noop;
+    Token::Keyword("typedef", start) => typedef(stream, start)?;
+    Token::Keyword("return", start) => {
+      let (value, end) = expression(stream, vec![";"])?;
+      Statement::Return(value, start.to(end.span()))
+    };
+    Token::Keyword("int", start) => declaration(stream, start, Datatype::Type(Compound (Primitive::Int, 1), Qualifiers::default()), None)?;
+    Token::Keyword("float", start) => declaration(stream, start, Datatype::Type(Compound (Primitive::Float, 1), Qualifiers::default()), None)?;
+    Token::Keyword("short", start) => declaration(stream, start, Datatype::Type(Compound (Primitive::Short, 1), Qualifiers::default()), None)?;
+    Token::Keyword("long", start) => declaration(stream, start, Datatype::Type(Compound (Primitive::Long, 1), Qualifiers::default()), None)?;
+    Token::Keyword("char", start) => declaration(stream, start, Datatype::Type(Compound (Primitive::Byte, 1), Qualifiers::default()), None)?;
+    Token::Typename(name, start) => declaration(stream, start, Datatype::Alias(name, start, Qualifiers::default()), None)?;
+    Token::Identifier(identifier, start) => match stream.peek() {
+      Some(Token::Identifier(..)) => declaration(stream, start, Datatype::Alias(identifier, start, Qualifiers::default()), None)?,
+      Some(&Token::Symbol(":", end)) => {
+        stream.next();
+        Statement::Label(identifier, start.to(end))
+      }
+      _ => statement_expression(stream, identifier, start)?,
+    };
+    x @ Token::Keyword("const" | "volatile" | "static" | "extern", _) => specified(stream, x)?;
+    Token::Keyword("for", start) => repetition(stream, start)?;
+    Token::Keyword("switch", start) => selection(stream, start)?;
+    Token::Keyword("case", start) => label(stream, start)?;
+    Token::Keyword("default", start), Token::Symbol(":", end) => Statement::Case(Case { value: None }, start.to(end));
+    Token::Keyword("break", start), Token::Symbol(";", end) => Statement::Break(start.to(end));
+    Token::Keyword("goto", start), Token::Identifier(label, _), Token::Symbol(";", end) => Statement::Goto(label, start.to(end));
+    Token::Symbol(";", span) => Statement::Noop(span);
 );
 
 syntax!(
   datatype() -> Datatype<'a>:
-    Token::Keyword("int") => Datatype::Type(Compound (Primitive::Int, 1));
-    Token::Keyword("float") => Datatype::Type(Compound (Primitive::Float, 1));
-    Token::Keyword("short") => Datatype::Type(Compound (Primitive::Short, 1));
-    Token::Keyword("long") => Datatype::Type(Compound (Primitive::Long, 1));
-    Token::Keyword("char") => Datatype::Type(Compound (Primitive::Byte, 1));
-    Token::Identifier(identifier) => Datatype::Alias(identifier);
+    Token::Keyword("int", _) => Datatype::Type(Compound (Primitive::Int, 1), Qualifiers::default());
+    Token::Keyword("float", _) => Datatype::Type(Compound (Primitive::Float, 1), Qualifiers::default());
+    Token::Keyword("short", _) => Datatype::Type(Compound (Primitive::Short, 1), Qualifiers::default());
+    Token::Keyword("long", _) => Datatype::Type(Compound (Primitive::Long, 1), Qualifiers::default());
+    Token::Keyword("char", _) => Datatype::Type(Compound (Primitive::Byte, 1), Qualifiers::default());
+    Token::Typename(name, span) => Datatype::Alias(name, span, Qualifiers::default());
+    Token::Identifier(identifier, span) => Datatype::Alias(identifier, span, Qualifiers::default());
 );
 
 syntax!(
   symbol(symbol: &str) -> Token<'a>:
-    x if x == Token::Symbol(symbol) => x;
+    x @ Token::Symbol(y, _) if y == symbol => x;
 );
 
 syntax!(
-  identifier() with stream -> (&'a str, usize):
-    Token::Identifier(identifier) => (identifier, index(stream).unwrap_or(0));
+  identifier() with stream -> (&'a str, Span, Option<Extent<'a>>):
+    Token::Identifier(identifier, span) => match stream.peek() {
+      Some(Token::Symbol("[", _)) => (identifier, span, Some(extent(stream)?)),
+      _ => (identifier, span, None),
+    };
 );
 
+// Array size, which is left to be inferred from the initializer when omitted
 syntax!(
-  index() -> usize:
-    Token::Symbol("["), Token::Data(Literal::Integer(size), _), Token::Symbol("]") => size as usize;
+  extent() with stream -> Extent<'a>:
+    Token::Symbol("[", _) => match stream.peek() {
+      Some(Token::Symbol("]", _)) => {
+        stream.next();
+        Extent::Inferred
+      }
+      _ => Extent::Size(expression(stream, vec!["]"])?.0),
+    };
 );
 
 syntax!(
   literal() -> Value<'a>:
-  Token::Data(Literal::Character(x), _) => Value::Data(Data::Byte(x as i8));
-  Token::Data(Literal::Integer(x), _) => Value::Data(Data::Integer(x));
-  Token::Data(Literal::Floating(x), _) => Value::Data(Data::Float(x));
-  Token::Data(Literal::Long(x), _) => Value::Data(Data::Long(x));
+  Token::Data(Literal::Character(x), ..) => Value::Data(Data::Byte(x as i8));
+  Token::Data(Literal::Integer(x), ..) => Value::Data(Data::Integer(x));
+  Token::Data(Literal::Floating(x), ..) => Value::Data(Data::Float(x));
+  Token::Data(Literal::Long(x), ..) => Value::Data(Data::Long(x));
 );
 
 syntax!(
-  unary_operator() -> UnaryOperator:
-    Token::Symbol("-") => UnaryOperator::Negation;
-    Token::Symbol("!") => UnaryOperator::Inversion;
+  prefix_operator() -> Prefix:
+    Token::Symbol("-", _) => Prefix::Unary(UnaryOperator::Negation);
+    Token::Symbol("+", _) => Prefix::Unary(UnaryOperator::Identity);
+    Token::Symbol("!", _) => Prefix::Unary(UnaryOperator::Inversion);
+    Token::Symbol("~", _) => Prefix::Unary(UnaryOperator::Complement);
+    Token::Symbol("++", _) => Prefix::Update(UpdateOperator::Increment);
+    Token::Symbol("--", _) => Prefix::Update(UpdateOperator::Decrement);
+    Token::Symbol("*", _) => Prefix::Dereference;
+    Token::Symbol("&", _) => Prefix::Reference;
 );
 
 syntax!(
-  binary_operator() -> BinaryOperator:
-    Token::Symbol("+") => BinaryOperator::Addition;
-    Token::Symbol("-") => BinaryOperator::Subtraction;
-    Token::Symbol("/") => BinaryOperator::Division;
-    Token::Symbol("*") => BinaryOperator::Multiplication;
-    Token::Symbol("%") => BinaryOperator::Remainder;
-    Token::Symbol(">") => BinaryOperator::Greater;
-    Token::Symbol("<") => BinaryOperator::Less;
-    Token::Symbol(">=") => BinaryOperator::GreaterEqual;
-    Token::Symbol("<=") => BinaryOperator::LessEqual;
-    Token::Symbol("==") => BinaryOperator::Equal;
-    Token::Symbol("!=") => BinaryOperator::NotEqual;
-    Token::Symbol("&&") => BinaryOperator::And;
-    Token::Symbol("||") => BinaryOperator::Or;
-    Token::Symbol("&") => BinaryOperator::BitwiseAnd;
-    Token::Symbol("|") => BinaryOperator::BitwiseOr;
-    Token::Symbol("^") => BinaryOperator::BitwiseXor;
-    Token::Symbol("<<") => BinaryOperator::LeftShift;
-    Token::Symbol(">>") => BinaryOperator::RightShift;
+  postfix_operator() -> Postfix:
+    Token::Symbol("++", _) => Postfix::Update(UpdateOperator::Increment);
+    Token::Symbol("--", _) => Postfix::Update(UpdateOperator::Decrement);
+    Token::Symbol("[", _) => Postfix::Index;
+    Token::Symbol("(", _) => Postfix::Call;
+    Token::Symbol(".", _) => Postfix::Member;
+    Token::Symbol("->", _) => Postfix::Member;
+);
+
+syntax!(
+  infix_operator() -> Infix:
+    Token::Symbol("+", _) => Infix::Binary(BinaryOperator::Addition);
+    Token::Symbol("-", _) => Infix::Binary(BinaryOperator::Subtraction);
+    Token::Symbol("/", _) => Infix::Binary(BinaryOperator::Division);
+    Token::Symbol("*", _) => Infix::Binary(BinaryOperator::Multiplication);
+    Token::Symbol("%", _) => Infix::Binary(BinaryOperator::Remainder);
+    Token::Symbol(">", _) => Infix::Binary(BinaryOperator::Greater);
+    Token::Symbol("<", _) => Infix::Binary(BinaryOperator::Less);
+    Token::Symbol(">=", _) => Infix::Binary(BinaryOperator::GreaterEqual);
+    Token::Symbol("<=", _) => Infix::Binary(BinaryOperator::LessEqual);
+    Token::Symbol("==", _) => Infix::Binary(BinaryOperator::Equal);
+    Token::Symbol("!=", _) => Infix::Binary(BinaryOperator::NotEqual);
+    Token::Symbol("&&", _) => Infix::Binary(BinaryOperator::And);
+    Token::Symbol("||", _) => Infix::Binary(BinaryOperator::Or);
+    Token::Symbol("&", _) => Infix::Binary(BinaryOperator::BitwiseAnd);
+    Token::Symbol("|", _) => Infix::Binary(BinaryOperator::BitwiseOr);
+    Token::Symbol("^", _) => Infix::Binary(BinaryOperator::BitwiseXor);
+    Token::Symbol("<<", _) => Infix::Binary(BinaryOperator::LeftShift);
+    Token::Symbol(">>", _) => Infix::Binary(BinaryOperator::RightShift);
+    Token::Symbol("=", _) => Infix::Assignment(None);
+    Token::Symbol("+=", _) => Infix::Assignment(Some(BinaryOperator::Addition));
+    Token::Symbol("-=", _) => Infix::Assignment(Some(BinaryOperator::Subtraction));
+    Token::Symbol("/=", _) => Infix::Assignment(Some(BinaryOperator::Division));
+    Token::Symbol("*=", _) => Infix::Assignment(Some(BinaryOperator::Multiplication));
+    Token::Symbol("%=", _) => Infix::Assignment(Some(BinaryOperator::Remainder));
+    Token::Symbol("&=", _) => Infix::Assignment(Some(BinaryOperator::BitwiseAnd));
+    Token::Symbol("|=", _) => Infix::Assignment(Some(BinaryOperator::BitwiseOr));
+    Token::Symbol("^=", _) => Infix::Assignment(Some(BinaryOperator::BitwiseXor));
+    Token::Symbol("<<=", _) => Infix::Assignment(Some(BinaryOperator::LeftShift));
+    Token::Symbol(">>=", _) => Infix::Assignment(Some(BinaryOperator::RightShift));
+    Token::Symbol("?", _) => Infix::Conditional;
+    Token::Symbol(",", _) => Infix::Comma;
 );
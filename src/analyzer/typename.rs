@@ -0,0 +1,73 @@
+use crate::{Token, TokenStream};
+use std::collections::HashSet;
+
+/// Marks identifiers declared by `typedef` as type names while the tokens pass through,
+/// so that the parser can tell a `(name)` cast apart from a parenthesized expression
+pub struct Typenames<'a, T: TokenStream<'a>> {
+    stream: T,
+    names: HashSet<&'a str>,
+    /// Ordinary identifiers that hide type names, one set per block
+    shadowed: Vec<HashSet<&'a str>>,
+    previous: Option<Token<'a>>,
+    typedef: bool,
+    declared: Option<&'a str>,
+}
+
+impl<'a, T: TokenStream<'a>> Typenames<'a, T> {
+    pub fn new(stream: T) -> Self {
+        Typenames {
+            stream,
+            names: HashSet::new(),
+            shadowed: vec![HashSet::new()],
+            previous: None,
+            typedef: false,
+            declared: None,
+        }
+    }
+
+    fn is_typename(&self, name: &str) -> bool {
+        self.names.contains(name) && !self.shadowed.iter().any(|x| x.contains(name))
+    }
+}
+
+impl<'a, T: TokenStream<'a>> Iterator for Typenames<'a, T> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let declarator = matches!(
+            self.previous,
+            Some(
+                Token::Typename(..)
+                    | Token::Keyword("int" | "float" | "short" | "long" | "char", _)
+            )
+        );
+
+        let token = match self.stream.next()? {
+            // A name right after a type is being declared, so it is never a type name itself
+            Token::Identifier(name, span) if declarator && !self.typedef => {
+                self.shadowed.last_mut().unwrap().insert(name);
+                Token::Identifier(name, span)
+            }
+            Token::Identifier(name, span) if self.is_typename(name) => Token::Typename(name, span),
+            token => token,
+        };
+
+        // The last identifier of a `typedef` is the name it declares
+        match token {
+            Token::Keyword("typedef", _) => self.typedef = true,
+            Token::Identifier(name, _) if self.typedef => self.declared = Some(name),
+            Token::Symbol(";", _) if self.typedef => {
+                self.names.extend(self.declared.take());
+                self.typedef = false;
+            }
+            Token::Symbol("{", _) => self.shadowed.push(HashSet::new()),
+            Token::Symbol("}", _) if self.shadowed.len() > 1 => {
+                self.shadowed.pop();
+            }
+            _ => (),
+        }
+
+        self.previous = Some(token);
+        Some(token)
+    }
+}
@@ -14,7 +14,7 @@ impl ImmediateCompat for f32 {
         for r in -3..=4 {
             let factor = 2_f32.powi(r);
             let n = (*self / factor * 16.0).round();
-            if n >= 16.0 && n <= 31.0 && (*self - (n / 16.0 * factor)).abs() < 1e-7 {
+            if (16.0..=31.0).contains(&n) && (*self - (n / 16.0 * factor)).abs() < 1e-7 {
                 return true;
             }
         }
@@ -32,7 +32,7 @@ impl ImmediateCompat for f32 {
 
 impl ImmediateCompat for i64 {
     fn can_be_immediate(&self) -> bool {
-        self.abs() <= 2_i64.pow(16)
+        self.unsigned_abs() <= 2_u64.pow(16)
     }
 
     fn represent(&self) -> String {
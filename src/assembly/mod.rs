@@ -2,40 +2,161 @@ mod arm;
 mod operation;
 
 use crate::{
-    ast::Primitive, error::assembly::AssemblyError, intermediate::Operand, program::Program,
+    ast::{Compound, Data, Primitive},
+    error::assembly::AssemblyError,
+    intermediate::Operand,
+    program::Program,
 };
 use arm::*;
 use operation::AssemblablePart;
 use std::collections::HashMap;
 
+/// Object format that the assembly is written for, which decides the names of the sections,
+/// the symbols and the relocations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// Mach-O objects of macOS
+    MacOS,
+    /// ELF objects of Linux, whose symbols also carry their types and sizes
+    Linux,
+}
+
 pub trait Assemblable {
-    fn assemble(self) -> Result<String, AssemblyError>;
+    fn assemble(self, target: Target) -> Result<String, AssemblyError>;
 }
 
-impl Assemblable for Program<'_> {
-    fn assemble(self) -> Result<String, AssemblyError> {
-        let globals = globals(&self)?;
-        let main = main(&self)?;
+impl Assemblable for Program {
+    fn assemble(self, target: Target) -> Result<String, AssemblyError> {
+        let globals = globals(&self, target)?;
+        let main = main(&self, target)?;
 
+        let text = match target {
+            Target::MacOS => ".section __TEXT,__text",
+            Target::Linux => ".text",
+        };
+        let (header, footer) = match target {
+            Target::MacOS => ("", ""),
+            Target::Linux => (".type main, %function\n", "\n.size main, .-main"),
+        };
         Ok(format!(
-            "{}.global main\nmain:\n{}",
+            "{}.global main\n{header}main:\n{main}{footer}",
             if globals.is_empty() {
                 "".to_owned()
             } else {
-                format!(".section __DATA,__data\n{globals}\n\n.section __TEXT,__text\n")
+                format!("{globals}{text}\n")
             },
-            main
         ))
     }
 }
 
-fn globals(program: &Program) -> Result<String, AssemblyError> {
-    program
+/// Writable globals go to the data section and constant ones to the read-only one.
+/// Globals that are all zeros only reserve their space, so they take none in the executable.
+/// Externals are defined by another object, so they are only referred to
+fn globals(program: &Program, target: Target) -> Result<String, AssemblyError> {
+    let (zeros, data): (HashMap<_, _>, HashMap<_, _>) = program
         .globals
+        .iter()
+        .map(|(name, entry)| (name.clone(), entry.clone()))
+        .partition(|(_, (_, values))| values.iter().all(|x| x.is_zero()));
+    let sections = match target {
+        Target::MacOS => [
+            ("__DATA,__data", &data),
+            ("__TEXT,__const", &program.constants),
+        ],
+        Target::Linux => [(".data", &data), (".rodata", &program.constants)],
+    };
+
+    let mut result = String::new();
+    for (section, entries) in sections {
+        if !entries.is_empty() {
+            let definitions = definitions(program, target, entries)?;
+            result += &format!(".section {section}\n{definitions}\n\n");
+        }
+    }
+    if zeros.is_empty() {
+        return Ok(result);
+    }
+
+    // ELF has no zerofill, its bss section is made of labels that only reserve their space
+    let zerofills: Vec<_> = zeros
+        .into_iter()
+        .map(|(name, (datatype, _))| {
+            let (size, alignment) = (datatype.size(), alignment(datatype));
+            let symbol = symbol(program, target, &name);
+            let visibility = visibility(program, target, &name);
+            match target {
+                Target::MacOS => {
+                    format!("{visibility}.zerofill __DATA,__bss,{symbol},{size},{alignment}")
+                }
+                Target::Linux => format!(
+                    "{visibility}.p2align {alignment}\n{}{symbol}:\n  .zero {size}",
+                    object(target, &symbol, datatype)
+                ),
+            }
+        })
+        .collect();
+    let section = match target {
+        Target::MacOS => "",
+        Target::Linux => ".bss\n",
+    };
+    result += &format!("{section}{}\n\n", zerofills.join("\n"));
+    Ok(result)
+}
+
+/// Power of two that the variable's address is a multiple of, so its elements are aligned
+fn alignment(datatype: Compound) -> u32 {
+    datatype.0.size().trailing_zeros()
+}
+
+/// Name of the global in the object, exported globals go by their names in C,
+/// with the underscore of the Mach-O symbols. The others are local to the object
+fn symbol(program: &Program, target: Target, identifier: &str) -> String {
+    if !program.exported.contains(identifier) {
+        return local(target, identifier);
+    }
+    let name = identifier.rsplit_once('_').unwrap().0;
+    match target {
+        Target::MacOS => format!("_{name}"),
+        Target::Linux => name.to_owned(),
+    }
+}
+
+/// Name that the assembler keeps to itself, so it cannot clash with the symbols of C
+fn local(target: Target, name: &str) -> String {
+    match target {
+        Target::MacOS => format!("L{name}"),
+        Target::Linux => format!(".L{name}"),
+    }
+}
+
+fn visibility(program: &Program, target: Target, identifier: &str) -> String {
+    match program.exported.contains(identifier) {
+        true => format!(".globl {}\n", symbol(program, target, identifier)),
+        false => "".to_owned(),
+    }
+}
+
+/// Type and size of the global's symbol, which only ELF keeps track of
+fn object(target: Target, symbol: &str, datatype: Compound) -> String {
+    match target {
+        Target::MacOS => "".to_owned(),
+        Target::Linux => format!(
+            ".type {symbol}, %object\n.size {symbol}, {}\n",
+            datatype.size()
+        ),
+    }
+}
+
+fn definitions(
+    program: &Program,
+    target: Target,
+    entries: &HashMap<String, (Compound, Vec<Data>)>,
+) -> Result<String, AssemblyError> {
+    entries
         .iter()
         .map(|(name, (datatype, values))| {
             let definitions = values
-                .into_iter()
+                .iter()
                 .map(|value| {
                     let data = if datatype.0.floating() {
                         f32::from(value).to_bits() as i64
@@ -50,6 +171,7 @@ fn globals(program: &Program) -> Result<String, AssemblyError> {
                         _ => {
                             return Err(AssemblyError {
                                 message: format!("Unsupported datatype: {datatype:?}"),
+                                span: None,
                             })
                         }
                     };
@@ -57,13 +179,26 @@ fn globals(program: &Program) -> Result<String, AssemblyError> {
                 })
                 .collect::<Result<Vec<_>, AssemblyError>>();
 
-            Ok(format!("{name}:\n{}", definitions?.join("\n")))
+            // Elements after the listed ones are zero-filled, so the whole variable is defined
+            let mut definitions = definitions?;
+            let rest = datatype.1.saturating_sub(values.len()) * datatype.0.size();
+            if rest > 0 {
+                definitions.push(format!("  .zero {rest}"));
+            }
+            let symbol = symbol(program, target, name);
+            Ok(format!(
+                "{}.p2align {}\n{}{symbol}:\n{}",
+                visibility(program, target, name),
+                alignment(*datatype),
+                object(target, &symbol, *datatype),
+                definitions.join("\n")
+            ))
         })
         .intersperse(Ok("\n".to_owned()))
         .collect()
 }
 
-fn main<'a>(program: &'a Program) -> Result<String, AssemblyError> {
+fn main(program: &Program, target: Target) -> Result<String, AssemblyError> {
     let mut instructions = vec![format!("sub sp, sp, {}", program.stack_size())];
     let mut addresses: HashMap<usize, usize> = HashMap::new();
     let mut stack: HashMap<String, usize> = HashMap::new();
@@ -77,17 +212,15 @@ fn main<'a>(program: &'a Program) -> Result<String, AssemblyError> {
             .iter()
             .position(|&x| x <= address)
             .ok_or(AssemblyError {
-                message: format!("Compilation impossible! Ran out of registers!"),
+                message: "Compilation impossible! Ran out of registers!".to_string(),
+                span: None,
             })?;
 
         if !temp {
             registers[index] = program
                 .instructions
                 .iter()
-                .rposition(|x| {
-                    x.operand1 == Operand::Address(address)
-                        || x.operand2 == Operand::Address(address)
-                })
+                .rposition(|x| x.operand1.uses(address) || x.operand2.uses(address))
                 .unwrap_or(address);
             addresses.insert(address, index);
         }
@@ -99,9 +232,12 @@ fn main<'a>(program: &'a Program) -> Result<String, AssemblyError> {
         let offset = index * program.type_of(identifier).unwrap().size();
         match stack.get(identifier) {
             Some(&pointer) => Ok(format!("[sp, {}]", pointer + offset)),
-            None => match program.globals.get(identifier) {
-                Some(_) => Ok(format!("{identifier}@{offset}")),
-                _ => {
+            None => match program.globals.contains_key(identifier)
+                || program.constants.contains_key(identifier)
+                || program.externals.contains_key(identifier)
+            {
+                true => Ok(format!("{}@{offset}", symbol(program, target, identifier))),
+                false => {
                     let all: usize = *stack.values().min().unwrap_or(&program.stack_size());
                     let pointer = all - program.locals.get(identifier).unwrap().size();
                     stack.insert(identifier.to_owned(), pointer);
@@ -118,7 +254,7 @@ fn main<'a>(program: &'a Program) -> Result<String, AssemblyError> {
      -> Result<String, AssemblyError> {
         Ok(match operand {
             Operand::Identifier(x, offset) => lookup(x, *offset)?,
-            Operand::Label(label) => label.clone(),
+            Operand::Label(label) => local(target, label),
             Operand::Data(x) => x.represent(),
             Operand::Asm(x) => x.to_string(),
             Operand::None => "".to_owned(),
@@ -126,6 +262,7 @@ fn main<'a>(program: &'a Program) -> Result<String, AssemblyError> {
                 "{}{}",
                 as_register(datatype.ok_or(AssemblyError {
                     message: format!("Unable to infer a type for register at {address}!"),
+                    span: None,
                 })?),
                 allocate(&mut addresses, address, temp)?
             ),
@@ -133,28 +270,45 @@ fn main<'a>(program: &'a Program) -> Result<String, AssemblyError> {
                 "{}{}",
                 as_register(datatype.ok_or(AssemblyError {
                     message: format!("Operation at {x} does not have a type!"),
+                    span: None,
                 })?),
                 addresses.get(x).ok_or(AssemblyError {
-                    message: format!("Operation at {x} does not have a result register!")
+                    message: format!("Operation at {x} does not have a result register!"),
+                    span: None,
+                })?
+            ),
+            Operand::Pointer(x, _) => format!(
+                "[x{}]",
+                addresses.get(x).ok_or(AssemblyError {
+                    message: format!("Operation at {x} does not have a result register!"),
+                    span: None,
                 })?
             ),
         })
     };
 
     for (address, cmd) in program.instructions.iter().enumerate() {
+        // Failures point at the code that the instruction was translated from
+        let locate = |error: AssemblyError| AssemblyError {
+            span: Some(cmd.span),
+            ..error
+        };
         let result_type = cmd.datatype(program);
         let lhs_type = cmd.operand1.datatype(program).or(result_type);
         let rhs_type = cmd.operand2.datatype(program).or(result_type);
 
-        let lhs = process_operand(&cmd.operand1, address, lhs_type, false)?;
-        let rhs = process_operand(&cmd.operand2, address, rhs_type, false)?;
+        let lhs = process_operand(&cmd.operand1, address, lhs_type, false).map_err(locate)?;
+        let rhs = process_operand(&cmd.operand2, address, rhs_type, false).map_err(locate)?;
 
         let allocate = |temp: bool, datatype: Option<Primitive>| {
             let address = if temp { 0 } else { address };
             process_operand(&Operand::Temp, address, datatype, temp)
         };
 
-        instructions.extend(cmd.operation.assemble(allocate, result_type, lhs, rhs)?);
+        let assembled = cmd
+            .operation
+            .assemble(allocate, target, result_type, lhs, rhs);
+        instructions.extend(assembled.map_err(locate)?);
     }
 
     let index = if let Some(x) = instructions.last()
@@ -177,7 +331,7 @@ fn main<'a>(program: &'a Program) -> Result<String, AssemblyError> {
         })
         .collect();
 
-    return Ok(formatted_instructions.join("\n"));
+    Ok(formatted_instructions.join("\n"))
 }
 
 fn as_register(datatype: Primitive) -> &'static str {
@@ -187,3 +341,71 @@ fn as_register(datatype: Primitive) -> &'static str {
         Primitive::Long => "x",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Target;
+    use crate::testing::{assemble, assemble_for};
+
+    /// Labels that the assembly defines, which are not allowed to repeat
+    fn assert_unique_labels(assembly: &str) {
+        let mut labels: Vec<_> = assembly.lines().filter(|x| x.ends_with(':')).collect();
+        labels.sort();
+        for pair in labels.windows(2) {
+            assert_ne!(pair[0], pair[1], "{assembly}");
+        }
+    }
+
+    /// Generated labels are local to the object, so globals can be named like them
+    #[test]
+    fn generated_labels() {
+        let text = "int case_0; int case_1; int switch_end_0; int switch_end_1; int main() { switch (case_0) { case 1: break; case 2: break; } return case_1 + switch_end_0 + switch_end_1; }";
+        for target in [Target::Linux, Target::MacOS] {
+            let assembly = assemble_for(text, target);
+            assert_unique_labels(&assembly);
+        }
+        let assembly = assemble_for(text, Target::Linux);
+        assert!(assembly.contains("\n.Lswitch_end_"), "{assembly}");
+        assert!(assembly.contains("\nswitch_end_0:"), "{assembly}");
+    }
+
+    /// Static variables keep their unique identifiers as local symbols,
+    /// so the exported globals that are spelled like them do not clash
+    #[test]
+    fn static_symbols() {
+        let text = "static int x; int x_0; int n_1; int main() { static int n = 1; return x + x_0 + n + n_1; }";
+        for target in [Target::Linux, Target::MacOS] {
+            let assembly = assemble_for(text, target);
+            assert_unique_labels(&assembly);
+        }
+        let assembly = assemble_for(text, Target::Linux);
+        for label in ["\n.Lx_0:", "\nx_0:", "\n.Ln_1:", "\nn_1:"] {
+            assert!(assembly.contains(label), "{label}:\n{assembly}");
+        }
+    }
+
+    /// ELF symbols carry their types and sizes, the Mach-O ones have neither
+    #[test]
+    fn symbols() {
+        let text = "int counter; const long c[2] = {1}; char s[] = \"hi\"; int main() { counter = c[0]; return counter + s[0]; }";
+        let assembly = assemble_for(text, Target::Linux);
+        for directive in [
+            ".bss\n.globl counter\n.p2align 2\n.type counter, %object\n.size counter, 4\ncounter:",
+            ".section .rodata\n.globl c\n.p2align 3\n.type c, %object\n.size c, 16\nc:",
+            ".type s, %object\n.size s, 3\ns:",
+            ".type main, %function\nmain:",
+            ".size main, .-main",
+            ":got_lo12:counter]",
+        ] {
+            assert!(assembly.contains(directive), "{directive}:\n{assembly}");
+        }
+
+        let assembly = assemble(text);
+        assert!(!assembly.contains(".type"), "{assembly}");
+        assert!(!assembly.contains(".size"), "{assembly}");
+        assert!(
+            assembly.contains(".zerofill __DATA,__bss,_counter,4,2"),
+            "{assembly}"
+        );
+    }
+}
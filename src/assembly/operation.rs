@@ -1,9 +1,11 @@
+use super::Target;
 use crate::{ast::Primitive, error::assembly::AssemblyError, intermediate::Operation};
 
 pub trait AssemblablePart {
     fn assemble<T: FnMut(bool, Option<Primitive>) -> Result<String, AssemblyError>>(
         &self,
         allocate: T,
+        target: Target,
         datatype: Option<Primitive>,
         lhs: String,
         rhs: String,
@@ -16,6 +18,7 @@ impl AssemblablePart for Operation {
     fn assemble<T: FnMut(bool, Option<Primitive>) -> Result<String, AssemblyError>>(
         &self,
         mut allocate: T,
+        target: Target,
         datatype: Option<Primitive>,
         lhs: String,
         rhs: String,
@@ -31,27 +34,73 @@ impl AssemblablePart for Operation {
                 vec![format!("ldr {lhs}, {rhs}")]
             }
             Operation::Lbl => vec![format!("{}:", lhs)],
+            // Picks the first operand when the last comparison was not equal
+            Operation::CSel => {
+                let result = allocate(false, datatype)?;
+                vec![format!(
+                    "{} {result}, {lhs}, {rhs}, ne",
+                    self.instruction(datatype)?
+                )]
+            }
+            Operation::Adr => match rhs.strip_prefix("[sp, ").and_then(|x| x.strip_suffix("]")) {
+                Some(offset) => vec![format!("add {lhs}, sp, {offset}")],
+                // Labels are addressed relative to the program counter
+                None => vec![format!("adr {lhs}, {rhs}")],
+            },
+            // The lower half of a register already holds the truncated value
+            Operation::Narrow => {
+                let register = lhs.strip_prefix("x").ok_or(AssemblyError {
+                    message: format!("Operand on narrowing instruction is invalid: {lhs}"),
+                    span: None,
+                })?;
+                let result = allocate(false, datatype)?;
+                vec![format!("mov {result}, w{register}")]
+            }
+            Operation::Adg => {
+                let (identifier, offset) = rhs.split_once("@").ok_or(AssemblyError {
+                    message: format!("Operand on global address instruction is invalid: {rhs}"),
+                    span: None,
+                })?;
+
+                let mut instructions = got(target, &lhs, identifier).to_vec();
+                if offset != "0" {
+                    instructions.push(format!("add {lhs}, {lhs}, {offset}"));
+                }
+                instructions
+            }
             Operation::Ldg => {
                 let temp = allocate(true, Some(Primitive::Long))?;
                 let (identifier, offset) = rhs.split_once("@").ok_or(AssemblyError {
                     message: format!("Operand on global load instruction is invalid: {rhs}"),
+                    span: None,
                 })?;
 
+                // Narrow globals are loaded by their own width, as their neighbours follow them
+                let [page, address] = got(target, &temp, identifier);
                 vec![
-                    format!("adrp {temp}, {identifier}@GOTPAGE"),
-                    format!("ldr {temp}, [{temp}, {identifier}@GOTPAGEOFF]"),
-                    format!("ldr {lhs}, [{temp}, {offset}]"),
+                    page,
+                    address,
+                    format!(
+                        "{} {lhs}, [{temp}, {offset}]",
+                        Operation::Ldr.instruction(datatype)?
+                    ),
                 ]
             }
             Operation::Stg => {
                 let temp = allocate(true, Some(Primitive::Long))?;
                 let (identifier, offset) = lhs.split_once("@").ok_or(AssemblyError {
-                    message: format!("Operand on global load instruction is invalid: {lhs}"),
+                    message: format!("Operand on global store instruction is invalid: {lhs}"),
+                    span: None,
                 })?;
 
+                let [page, address] = got(target, &temp, identifier);
                 vec![
-                    format!("adrp {temp}, {identifier}@PAGE"),
-                    format!("str {rhs}, [{temp}, {offset}]"),
+                    page,
+                    address,
+                    format!(
+                        "{} {rhs}, [{temp}, {offset}]",
+                        Operation::Str.instruction(datatype)?
+                    ),
                 ]
             }
             Operation::Ret => vec![
@@ -60,7 +109,7 @@ impl AssemblablePart for Operation {
                 } else {
                     None
                 },
-                Some(format!("ret")),
+                Some("ret".to_owned()),
             ]
             .into_iter()
             .flatten()
@@ -94,18 +143,30 @@ impl AssemblablePart for Operation {
             Self::Asr => "asr",
             Self::Lsl => "lsl",
             Self::CSet => "cset",
+            Self::CSel => "csel",
             Self::Cmp => "cmp",
             Self::Str => "str",
             Self::Ldr => "ldr",
             Self::Ldg => "ldg",
             Self::Stg => "stg",
             Self::Neg => "neg",
+            Self::Mvn => "mvn",
             Self::Ret => "ret",
             Self::FCvtZS => "fcvtzs",
             Self::SCvtF => "scvtf",
+            Self::SxtW => "sxtw",
+            Self::SxtH => "sxth",
+            Self::SxtB => "sxtb",
+            Self::Narrow => "mov",
+            Self::Adr => "adr",
+            Self::Adg => "adg",
             Self::Lbl => "",
             Self::B => "b",
             Self::BEq => "b.eq",
+            Self::BNe => "b.ne",
+            Self::BLt => "b.lt",
+            Self::BHi => "b.hi",
+            Self::Br => "br",
         };
 
         match (self, datatype) {
@@ -114,15 +175,19 @@ impl AssemblablePart for Operation {
                 None,
             ) => Err(AssemblyError {
                 message: format!("Instruction {self:?} requires a known datatype!"),
+                span: None,
             }),
-            (Self::Mov | Self::Add | Self::Mul | Self::Sub | Self::Div, Some(Primitive::Float)) => {
-                Ok(format!("f{op}"))
-            }
+            (
+                Self::Mov | Self::Add | Self::Mul | Self::Sub | Self::Div | Self::CSel | Self::Cmp,
+                Some(Primitive::Float),
+            ) => Ok(format!("f{op}")),
             (
                 Self::Div,
                 Some(Primitive::Byte | Primitive::Short | Primitive::Int | Primitive::Long),
             ) => Ok(format!("s{op}")),
-            (Self::Str | Self::Ldr, Some(Primitive::Byte)) => Ok(format!("{op}b")),
+            // Chars are signed like the other integers, so their loads extend the sign too
+            (Self::Ldr, Some(Primitive::Byte)) => Ok(format!("{op}sb")),
+            (Self::Str, Some(Primitive::Byte)) => Ok(format!("{op}b")),
             (Self::Ldr, Some(Primitive::Short)) => Ok(format!("{op}sh")),
             (Self::Str, Some(Primitive::Short)) => Ok(format!("{op}h")),
             _ => Ok(op.to_owned()),
@@ -140,13 +205,43 @@ impl AssemblablePart for Operation {
             | Operation::Eor
             | Operation::Asr
             | Operation::Lsl => (2, 1, false),
-            Operation::Neg | Operation::CSet | Operation::FCvtZS | Operation::SCvtF => {
-                (1, 1, false)
-            }
+            Operation::Neg
+            | Operation::Mvn
+            | Operation::CSet
+            | Operation::FCvtZS
+            | Operation::SCvtF
+            | Operation::SxtW
+            | Operation::SxtH
+            | Operation::SxtB
+            | Operation::Narrow => (1, 1, false),
             Operation::Cmp | Operation::Mov | Operation::Ldr => (2, 0, false),
-            Operation::Lbl | Operation::B | Operation::BEq => (1, 0, false),
-            Operation::Ret | Operation::Ldg | Operation::Stg => (0, 0, false),
+            Operation::CSel => (2, 1, false),
+            Operation::Lbl
+            | Operation::B
+            | Operation::BEq
+            | Operation::BNe
+            | Operation::BLt
+            | Operation::BHi
+            | Operation::Br => (1, 0, false),
+            Operation::Ret | Operation::Ldg | Operation::Stg | Operation::Adr | Operation::Adg => {
+                (0, 0, false)
+            }
             Operation::Str => (2, 0, true),
         }
     }
 }
+
+/// Instructions that load the address of the global into the register,
+/// through the entry of the global offset table that the linker fills in
+fn got(target: Target, register: &str, identifier: &str) -> [String; 2] {
+    match target {
+        Target::MacOS => [
+            format!("adrp {register}, {identifier}@GOTPAGE"),
+            format!("ldr {register}, [{register}, {identifier}@GOTPAGEOFF]"),
+        ],
+        Target::Linux => [
+            format!("adrp {register}, :got:{identifier}"),
+            format!("ldr {register}, [{register}, :got_lo12:{identifier}]"),
+        ],
+    }
+}
@@ -0,0 +1,185 @@
+use super::context::Context;
+use crate::{
+    ast::{BinaryOperator, Compound, Data, Primitive, UnaryOperator},
+    semantic::SemanticError,
+    typed::{Expression, Kind, Place},
+};
+use std::collections::HashMap;
+
+/// Globals along with their initial values, as they are defined by the checker
+pub type Globals = HashMap<String, (Compound, Vec<Data>)>;
+
+impl Expression {
+    /// Value of an integer constant expression
+    pub fn constant(&self) -> Option<i64> {
+        match self.fold(None) {
+            Ok(Data::Float(_)) | Err(_) => None,
+            Ok(data) => Some(i64::from(&data)),
+        }
+    }
+
+    /// Evaluates the expression at compile time with the wrapping integer and float semantics of C.
+    /// Constant globals can only be referred to by the initializers of the other globals,
+    /// which are given the context for that
+    pub fn fold(&self, context: Option<&Context>) -> Result<Data, SemanticError> {
+        if let Kind::Load(Place::Direct(symbol, offset)) = &self.kind
+            && let Some(values) = context.and_then(|x| x.constant(&symbol.identifier))
+        {
+            // Elements past the listed ones are zero-filled
+            let value = values.get(*offset).copied();
+            return Ok(convert(
+                value.unwrap_or(Data::zero(self.datatype)),
+                self.datatype,
+            ));
+        }
+
+        let unevaluable = |note: &str| SemanticError {
+            code: "E0426",
+            message: "Expression is not a compile-time constant!".to_owned(),
+            span: Some(self.span),
+            notes: vec![note.to_owned()],
+            ..Default::default()
+        };
+        let data = match &self.kind {
+            Kind::Data(data) => *data,
+            Kind::Conversion(value) => convert(value.fold(context)?, self.datatype),
+            Kind::Unary { op, lhs } => unary(*op, lhs.fold(context)?).map_err(unevaluable)?,
+            // The right side of a logical operator is not evaluated when the left one decides it
+            Kind::Binary {
+                op: op @ (BinaryOperator::And | BinaryOperator::Or),
+                lhs,
+                rhs,
+            } => match truthy(lhs.fold(context)?) {
+                true if *op == BinaryOperator::Or => Data::Integer(1),
+                false if *op == BinaryOperator::And => Data::Integer(0),
+                _ => Data::Integer(truthy(rhs.fold(context)?) as i32),
+            },
+            Kind::Binary { op, lhs, rhs } => {
+                let (a, b) = (lhs.fold(context)?, rhs.fold(context)?);
+                binary(*op, a, b).map_err(unevaluable)?
+            }
+            // Only the arm that is picked has to be constant
+            Kind::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => match truthy(condition.fold(context)?) {
+                true => then.fold(context)?,
+                false => otherwise.fold(context)?,
+            },
+            _ => {
+                return Err(SemanticError {
+                    code: "E0426",
+                    message: "Expression is not a compile-time constant!".to_owned(),
+                    span: Some(self.span),
+                    ..Default::default()
+                })
+            }
+        };
+        Ok(data)
+    }
+}
+
+/// Conversion of the constant, which truncates floats and wraps integers around
+pub fn convert(data: Data, to: Primitive) -> Data {
+    match (data, to) {
+        (Data::Float(x), Primitive::Float) => Data::Float(x),
+        (data, Primitive::Float) => Data::Float(i64::from(&data) as f32),
+        (data, Primitive::Long) => Data::Long(i64::from(&data)),
+        (data, Primitive::Int) => Data::Integer(i64::from(&data) as i32),
+        (data, Primitive::Short) => Data::Short(i64::from(&data) as i16),
+        (data, Primitive::Byte) => Data::Byte(i64::from(&data) as i8),
+    }
+}
+
+fn truthy(data: Data) -> bool {
+    match data {
+        Data::Float(x) => x != 0.0,
+        data => i64::from(&data) != 0,
+    }
+}
+
+/// Reasons why an operation has no value, which are noted on the error
+const ZERO: &str = "Division by zero cannot be evaluated";
+/// The checker already rejects these, so the note only shows if that is missed
+const INTEGRAL: &str = "Operator cannot be applied to a float";
+
+fn unary(op: UnaryOperator, data: Data) -> Result<Data, &'static str> {
+    if let Data::Float(x) = data {
+        return match op {
+            UnaryOperator::Negation => Ok(Data::Float(-x)),
+            UnaryOperator::Identity => Ok(Data::Float(x)),
+            UnaryOperator::Inversion => Ok(Data::Integer((x == 0.0) as i32)),
+            UnaryOperator::Complement => Err(INTEGRAL),
+        };
+    }
+
+    let value = i64::from(&data);
+    let value = match op {
+        UnaryOperator::Negation => value.wrapping_neg(),
+        UnaryOperator::Identity => value,
+        UnaryOperator::Inversion => return Ok(Data::Integer((value == 0) as i32)),
+        UnaryOperator::Complement => !value,
+    };
+    Ok(match data {
+        Data::Long(_) => Data::Long(value),
+        _ => Data::Integer(value as i32),
+    })
+}
+
+/// Operands are already converted to the same type, narrow integers are computed as ints
+/// just like they are in registers. Division by zero has no value
+fn binary(op: BinaryOperator, lhs: Data, rhs: Data) -> Result<Data, &'static str> {
+    let compare = |result: bool| Ok(Data::Integer(result as i32));
+    if let (Data::Float(_), _) | (_, Data::Float(_)) = (lhs, rhs) {
+        let (a, b) = (f32::from(&lhs), f32::from(&rhs));
+        return match op {
+            BinaryOperator::Addition => Ok(Data::Float(a + b)),
+            BinaryOperator::Subtraction => Ok(Data::Float(a - b)),
+            BinaryOperator::Multiplication => Ok(Data::Float(a * b)),
+            BinaryOperator::Division => Ok(Data::Float(a / b)),
+            BinaryOperator::Greater => compare(a > b),
+            BinaryOperator::Less => compare(a < b),
+            BinaryOperator::GreaterEqual => compare(a >= b),
+            BinaryOperator::LessEqual => compare(a <= b),
+            BinaryOperator::Equal => compare(a == b),
+            BinaryOperator::NotEqual => compare(a != b),
+            BinaryOperator::And => compare(a != 0.0 && b != 0.0),
+            BinaryOperator::Or => compare(a != 0.0 || b != 0.0),
+            _ => Err(INTEGRAL),
+        };
+    }
+
+    let long = matches!(lhs, Data::Long(_)) || matches!(rhs, Data::Long(_));
+    let (a, b) = (i64::from(&lhs), i64::from(&rhs));
+    // Shift amounts are taken modulo the width, as the hardware does
+    let shift = (b as u32) & if long { 63 } else { 31 };
+    let value = match op {
+        BinaryOperator::Addition => a.wrapping_add(b),
+        BinaryOperator::Subtraction => a.wrapping_sub(b),
+        BinaryOperator::Multiplication => a.wrapping_mul(b),
+        BinaryOperator::Division if b == 0 => return Err(ZERO),
+        BinaryOperator::Division => a.wrapping_div(b),
+        BinaryOperator::Remainder if b == 0 => return Err(ZERO),
+        BinaryOperator::Remainder => a.wrapping_rem(b),
+        BinaryOperator::BitwiseAnd => a & b,
+        BinaryOperator::BitwiseOr => a | b,
+        BinaryOperator::BitwiseXor => a ^ b,
+        BinaryOperator::LeftShift => a.wrapping_shl(shift),
+        BinaryOperator::RightShift => a >> shift,
+        BinaryOperator::Greater => return compare(a > b),
+        BinaryOperator::Less => return compare(a < b),
+        BinaryOperator::GreaterEqual => return compare(a >= b),
+        BinaryOperator::LessEqual => return compare(a <= b),
+        BinaryOperator::Equal => return compare(a == b),
+        BinaryOperator::NotEqual => return compare(a != b),
+        BinaryOperator::And => return compare(a != 0 && b != 0),
+        BinaryOperator::Or => return compare(a != 0 || b != 0),
+    };
+
+    // Ints are computed in the width of longs and wrapped around afterwards
+    Ok(match long {
+        true => Data::Long(value),
+        false => Data::Integer(value as i32),
+    })
+}
@@ -0,0 +1,404 @@
+use super::constant::Globals;
+use crate::{
+    ast::{Compound, Data, Datatype, Primitive, Qualifiers, Storage},
+    diagnostic::Label,
+    semantic::SemanticError,
+    typed::Symbol,
+    warning::{Category, Warning},
+    Span,
+};
+use std::{
+    cmp::{max, min},
+    collections::{HashMap, HashSet},
+};
+
+/// Labels of a switch, the accepted ones are pointed at by their duplicates
+pub struct Cases {
+    /// Type of the switch's value, which the cases are converted to
+    pub datatype: Primitive,
+    pub default: Option<Span>,
+    pub values: Vec<(i64, Span)>,
+}
+
+pub struct Context<'a> {
+    pub globals: Globals,
+    pub locals: HashMap<String, Compound>,
+    /// Storage classes of the variables that have one, they are kept along with the globals
+    pub storage: HashMap<String, Storage>,
+    /// Qualifiers of the variables that have any
+    pub qualifiers: HashMap<String, Qualifiers>,
+    /// Names of the variables along with their spans where they are defined
+    pub definitions: HashMap<String, (&'a str, Span)>,
+    pub warnings: Vec<Warning>,
+    /// Warnings that are reported, the others are dropped by `warn`
+    pub enabled: HashSet<Category>,
+    /// Errors of the failed parts, which are skipped so the checking can go on
+    pub errors: Vec<SemanticError>,
+
+    types: HashMap<&'a str, (Compound, Qualifiers)>,
+    /// Spans of the aliases' names where they are defined
+    typedefs: HashMap<&'a str, Span>,
+    /// Variables and aliases that are referred to, the others are reported as unused
+    used: HashSet<String>,
+    used_types: HashSet<&'a str>,
+    /// Top-level variables that are declared without a value so far
+    tentative: HashSet<String>,
+    /// Identifiers of the scopes that are entered, the top-level one is zero
+    scopes: Vec<usize>,
+    /// Last identifier given to a scope, so every scope has its own variable names
+    scope: usize,
+    /// Number of the enclosing loops and switches, which `break` can leave
+    breakable: usize,
+    /// Labels of the enclosing switches, the cases in loops belong to the innermost one
+    switches: Vec<Cases>,
+    /// Names used without a definition, along with the variables that stand in for them
+    undefined: HashMap<&'a str, String>,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(enabled: HashSet<Category>) -> Self {
+        Context {
+            scopes: vec![0],
+            scope: 0,
+            breakable: 0,
+            switches: Vec::new(),
+            types: HashMap::new(),
+            typedefs: HashMap::new(),
+            used: HashSet::new(),
+            used_types: HashSet::new(),
+            tentative: HashSet::new(),
+            warnings: Vec::new(),
+            enabled,
+            errors: Vec::new(),
+            locals: HashMap::new(),
+            storage: HashMap::new(),
+            qualifiers: HashMap::new(),
+            definitions: HashMap::new(),
+            globals: HashMap::new(),
+            undefined: HashMap::new(),
+        }
+    }
+
+    pub fn toplevel(&self) -> bool {
+        self.scopes.len() == 1
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scope += 1;
+        self.scopes.push(self.scope);
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Allows `break` until the matching `pop_break`
+    pub fn push_break(&mut self) {
+        self.breakable += 1;
+    }
+
+    pub fn pop_break(&mut self) {
+        self.breakable -= 1;
+    }
+
+    pub fn breakable(&self) -> bool {
+        self.breakable > 0
+    }
+
+    /// Gives the cases to a switch whose value has the datatype, until the matching `pop_switch`
+    pub fn push_switch(&mut self, datatype: Primitive) {
+        self.switches.push(Cases {
+            datatype,
+            default: None,
+            values: Vec::new(),
+        });
+    }
+
+    pub fn pop_switch(&mut self) {
+        self.switches.pop();
+    }
+
+    pub fn switch(&mut self) -> Option<&mut Cases> {
+        self.switches.last_mut()
+    }
+
+    /// Records the error of a failed part, so the checking can go on without it
+    pub fn recover<T>(&mut self, result: Result<T, SemanticError>) -> Option<T> {
+        result.map_err(|error| self.errors.push(error)).ok()
+    }
+
+    pub fn warn(&mut self, warning: Warning) {
+        if self.enabled.contains(&warning.category) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn infer_scope(&self, name: &'a str) -> Option<usize> {
+        self.scopes.iter().rev().copied().find(|&scope| {
+            // Static and external variables are kept along with the globals in any scope
+            self.compound_of(&format!("{name}_{scope}")).is_some()
+        })
+    }
+
+    /// Undefined names are reported once, then an int variable stands in for them,
+    /// so their other uses do not cause follow-up errors
+    pub fn infer_name(&mut self, name: &'a str, span: Span) -> String {
+        if let Some(scope) = self.infer_scope(name) {
+            let identifier = format!("{name}_{}", scope);
+            self.used.insert(identifier.clone());
+            return identifier;
+        }
+        if let Some(identifier) = self.undefined.get(name) {
+            return identifier.clone();
+        }
+
+        // Only the variables that are visible from here are suggested
+        let visible = self.definitions.iter().filter(|&(identifier, &(name, _))| {
+            self.infer_scope(name)
+                .is_some_and(|scope| *identifier == format!("{name}_{scope}"))
+        });
+        let (labels, notes) = suggest(name, visible.map(|(_, &x)| (x.0, Some(x.1))));
+        self.errors.push(SemanticError {
+            code: "E0401",
+            message: format!("'{}' is not defined!", name),
+            span: Some(span),
+            labels,
+            notes,
+        });
+        let identifier = format!("$undefined_{}", self.undefined.len());
+        self.locals
+            .insert(identifier.clone(), Compound(Primitive::Int, 1));
+        self.undefined.insert(name, identifier.clone());
+        identifier
+    }
+
+    /// Variable that the name refers to from the current scope
+    pub fn symbol(&mut self, name: &'a str, span: Span) -> Symbol {
+        let identifier = self.infer_name(name, span);
+        Symbol {
+            datatype: self.compound_of(&identifier).unwrap(),
+            global: self.globals.contains_key(&identifier),
+            qualifiers: self.qualifiers_of(&identifier),
+            identifier,
+        }
+    }
+
+    /// Identifier of the variable in the current scope, such as the one being initialized
+    pub fn local_name(&self, name: &'a str) -> String {
+        format!("{name}_{}", self.scopes.last().unwrap())
+    }
+
+    /// Values of the constant variable that are known ahead of time,
+    /// the ones that are only declared so far are not known yet
+    pub fn constant(&self, identifier: &str) -> Option<&[Data]> {
+        let qualifiers = self.qualifiers_of(identifier);
+        if !qualifiers.constant || qualifiers.volatile || self.tentative.contains(identifier) {
+            return None;
+        }
+        self.globals.get(identifier).map(|(_, values)| &values[..])
+    }
+
+    pub fn qualifiers_of(&self, identifier: &str) -> Qualifiers {
+        self.qualifiers.get(identifier).copied().unwrap_or_default()
+    }
+
+    /// Undefined aliases are reported once, then they are treated as ints.
+    /// Qualifiers of the alias add up with the ones it is used with
+    pub fn resolve_type(&mut self, datatype: Datatype<'a>) -> (Compound, Qualifiers) {
+        match datatype {
+            Datatype::Type(datatype, qualifiers) => (datatype, qualifiers),
+            Datatype::Alias(alias, span, qualifiers) => match self.types.get(alias) {
+                Some(&(datatype, aliased)) => {
+                    self.used_types.insert(alias);
+                    (datatype, aliased.union(qualifiers))
+                }
+                None => {
+                    let primitives = ["int", "float", "short", "long", "char"];
+                    let typedefs = self
+                        .typedefs
+                        .iter()
+                        .map(|(&name, &span)| (name, Some(span)));
+                    let candidates = primitives.into_iter().map(|x| (x, None)).chain(typedefs);
+                    let (labels, notes) = suggest(alias, candidates);
+                    self.errors.push(SemanticError {
+                        code: "E0402",
+                        message: format!("Type '{}' is not defined!", alias),
+                        span: Some(span),
+                        labels,
+                        notes,
+                    });
+                    let datatype = Compound(Primitive::Int, 1);
+                    self.types.insert(alias, (datatype, Qualifiers::default()));
+                    (datatype, qualifiers)
+                }
+            },
+        }
+    }
+
+    pub fn compound_of(&self, identifier: &str) -> Option<Compound> {
+        self.locals
+            .get(identifier)
+            .or_else(|| self.globals.get(identifier).map(|x| &x.0))
+            .copied()
+    }
+
+    /// Top-level variables without a value are tentative, so they can be declared again
+    /// with the same type, as long as only one of the declarations has a value.
+    /// Static and external variables are kept along with the globals
+    pub fn define_variable(
+        &mut self,
+        name: &'a str,
+        span: Span,
+        datatype: Datatype<'a>,
+        storage: Option<Storage>,
+        value: Option<Vec<Data>>,
+    ) -> Result<(), SemanticError> {
+        let (datatype, qualifiers) = self.resolve_type(datatype);
+        let identifier = self.local_name(name);
+        if let Some(defined) = self.compound_of(&identifier) {
+            let tentative = value.is_none() || self.tentative.contains(&identifier);
+            let same = defined == datatype && self.qualifiers_of(&identifier) == qualifiers;
+            if self.toplevel() && tentative && same {
+                // External declarations are defined by any other declaration
+                if storage != Some(Storage::Extern)
+                    && self.storage.get(&identifier) == Some(&Storage::Extern)
+                {
+                    self.storage.remove(&identifier);
+                }
+                if let Some(value) = value {
+                    self.tentative.remove(&identifier);
+                    self.globals.insert(identifier, (datatype, value));
+                }
+                return Ok(());
+            }
+
+            let (_, previous) = self.definitions[&identifier];
+            return Err(SemanticError {
+                code: "E0403",
+                message: format!("Variable '{}' is already defined in this scope!", name),
+                span: Some(span),
+                labels: vec![Label {
+                    span: previous,
+                    message: "Previously defined here".to_owned(),
+                }],
+                ..Default::default()
+            });
+        }
+
+        if let Some(scope) = self.infer_scope(name) {
+            let (_, previous) = self.definitions[&format!("{name}_{scope}")];
+            self.warn(Warning {
+                category: Category::Shadow,
+                message: format!("Declaration of '{}' shadows a previous one!", name),
+                span: Some(span),
+                labels: vec![Label {
+                    span: previous,
+                    message: "Shadowed declaration is here".to_owned(),
+                }],
+                notes: Vec::new(),
+            });
+        }
+
+        self.definitions.insert(identifier.clone(), (name, span));
+        if qualifiers != Qualifiers::default() {
+            self.qualifiers.insert(identifier.clone(), qualifiers);
+        }
+        if let Some(storage) = storage {
+            self.storage.insert(identifier.clone(), storage);
+        }
+        if self.toplevel() || storage.is_some() {
+            if value.is_none() {
+                self.tentative.insert(identifier.clone());
+            }
+            let value = value.unwrap_or_default();
+            self.globals.insert(identifier, (datatype, value));
+        } else {
+            self.locals.insert(identifier, datatype);
+        }
+
+        Ok(())
+    }
+
+    pub fn define_type(&mut self, name: &'a str, span: Span, datatype: Datatype<'a>) {
+        let datatype = self.resolve_type(datatype);
+        self.types.insert(name, datatype);
+        self.typedefs.insert(name, span);
+    }
+
+    /// Warns about the local variables and the aliases that are never referred to
+    pub fn check_unused(&mut self) {
+        let mut warnings = Vec::new();
+        for (identifier, &(name, span)) in &self.definitions {
+            if self.locals.contains_key(identifier) && !self.used.contains(identifier) {
+                warnings.push(Warning {
+                    category: Category::UnusedVariable,
+                    message: format!("Variable '{}' is never used!", name),
+                    span: Some(span),
+                    labels: Vec::new(),
+                    notes: Vec::new(),
+                });
+            }
+        }
+        for (&name, &span) in &self.typedefs {
+            if !self.used_types.contains(name) {
+                warnings.push(Warning {
+                    category: Category::UnusedTypedef,
+                    message: format!("Type '{}' is never used!", name),
+                    span: Some(span),
+                    labels: Vec::new(),
+                    notes: Vec::new(),
+                });
+            }
+        }
+
+        for warning in warnings {
+            self.warn(warning);
+        }
+    }
+}
+
+/// Suggestion of the closest candidate to the misspelled name, along with where it is defined
+fn suggest<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = (&'a str, Option<Span>)>,
+) -> (Vec<Label>, Vec<String>) {
+    // Ties are broken alphabetically, so the suggestion does not depend on the hashing
+    let threshold = max(name.len(), 3) / 3;
+    let closest = candidates
+        .map(|(candidate, span)| (distance(name, candidate), candidate, span))
+        .filter(|&(distance, ..)| distance <= threshold)
+        .min_by_key(|&(distance, candidate, _)| (distance, candidate));
+
+    let Some((_, candidate, span)) = closest else {
+        return (Vec::new(), Vec::new());
+    };
+    let labels = span.map(|span| Label {
+        span,
+        message: format!("'{}' is defined here", candidate),
+    });
+    let notes = vec![format!("Did you mean '{}'?", candidate)];
+    (labels.into_iter().collect(), notes)
+}
+
+/// Edit distance between the names, where swapping two adjacent characters is a single edit
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..=a.len() {
+        for j in 0..=b.len() {
+            table[i][j] = match (i, j) {
+                (0, j) => j,
+                (i, 0) => i,
+                (i, j) => {
+                    let substitution = table[i - 1][j - 1] + (a[i - 1] != b[j - 1]) as usize;
+                    let mut best = min(min(table[i - 1][j], table[i][j - 1]) + 1, substitution);
+                    if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                        best = min(best, table[i - 2][j - 2] + 1);
+                    }
+                    best
+                }
+            };
+        }
+    }
+    table[a.len()][b.len()]
+}
@@ -0,0 +1,1363 @@
+pub mod constant;
+pub mod context;
+
+use crate::{
+    ast::{
+        BinaryOperator, Case, Compound, Data, Datatype, Entry, Expression, Extent, Initializer,
+        Loop, Lvalue, Primitive, Qualifiers, Sizeof, Statement, Storage, Switch, UnaryOperator,
+        Value, Variable,
+    },
+    diagnostic::Label,
+    semantic::SemanticError,
+    typed::{self, Initialization, Kind, Place, Symbol},
+    warning::{Category, Warning},
+    Span,
+};
+use context::Context;
+use std::cmp::max;
+
+/// Resolves the names and types of the AST, so the translation gets a fully typed tree
+pub trait Checkable<'a> {
+    type Typed;
+    fn check(self, context: &mut Context<'a>) -> Result<Self::Typed, SemanticError>;
+}
+
+impl<'a> Checkable<'a> for Statement<'a> {
+    /// Declarations are resolved here and leave nothing to translate
+    type Typed = Option<typed::Statement<'a>>;
+
+    fn check(self, context: &mut Context<'a>) -> Result<Self::Typed, SemanticError> {
+        match self {
+            Self::Type(typedef, _) => {
+                let (compound, qualifiers) = dimensioned(context, typedef.datatype, typedef.extent);
+                let datatype = Datatype::Type(compound, qualifiers);
+                context.define_type(typedef.name, typedef.span, datatype);
+                Ok(None)
+            }
+            Self::Variables(variables, span) => {
+                let initializations = variables.check(context)?;
+                Ok(match initializations.is_empty() {
+                    true => None,
+                    false => Some(typed::Statement::Initializations(initializations, span)),
+                })
+            }
+            Self::Loop(repetition, span) => Ok(repetition
+                .check(context)?
+                .map(|x| typed::Statement::Loop(x, span))),
+            Self::Switch(selection, span) => Ok(Some(typed::Statement::Switch(
+                selection.check(context)?,
+                span,
+            ))),
+            Self::Case(case, span) => label(context, case, span),
+            Self::Break(span) => match context.breakable() {
+                true => Ok(Some(typed::Statement::Break(span))),
+                false => Err(SemanticError {
+                    code: "E0405",
+                    message: "'break' is not within a loop or switch statement!".to_owned(),
+                    span: Some(span),
+                    ..Default::default()
+                }),
+            },
+            Self::Expression(expression, span) => {
+                if context.toplevel() {
+                    return Err(SemanticError {
+                        code: "E0406",
+                        message: "Expressions are not allowed on the top-level!".to_owned(),
+                        span: Some(span),
+                        ..Default::default()
+                    });
+                }
+
+                let expression = expression.check(context)?;
+                Ok(Some(typed::Statement::Expression(expression, span)))
+            }
+            Self::Return(value, span) => {
+                if context.toplevel() {
+                    return Err(SemanticError {
+                        code: "E0406",
+                        message: "Return is not allowed on the top-level!".to_owned(),
+                        span: Some(span),
+                        ..Default::default()
+                    });
+                }
+
+                let value = value.check(context)?;
+                let value = cast(context, value, Primitive::Int);
+                Ok(Some(typed::Statement::Return(value, span)))
+            }
+            Self::Function(function, span) => {
+                // The body is still checked, so its errors are reported as well
+                if function.name != "main" {
+                    context.errors.push(SemanticError {
+                        code: "E0407",
+                        message: "Only 'main' function is supported by this implementation!"
+                            .to_owned(),
+                        span: Some(function.span),
+                        notes: vec!["Function calls are not supported yet".to_owned()],
+                        ..Default::default()
+                    });
+                }
+
+                check_jumps(context, &function.body);
+                context.push_scope();
+                let body = function.body.check(context)?;
+                context.pop_scope();
+                Ok(Some(typed::Statement::Function(body, span)))
+            }
+            Self::Label(_, span) | Self::Goto(_, span) if context.toplevel() => {
+                Err(SemanticError {
+                    code: "E0406",
+                    message: "Labels are not allowed on the top-level!".to_owned(),
+                    span: Some(span),
+                    ..Default::default()
+                })
+            }
+            Self::Label(name, span) => Ok(Some(typed::Statement::Label(name, span))),
+            Self::Goto(name, span) => Ok(Some(typed::Statement::Goto(name, span))),
+            Self::Noop(_) => Ok(None),
+            // Syntax errors are reported by the analyzer, so there is nothing left to check
+            Self::Error(..) => Ok(None),
+        }
+    }
+}
+
+impl<'a> Checkable<'a> for Vec<Statement<'a>> {
+    type Typed = Vec<typed::Statement<'a>>;
+
+    /// Failed statements are recorded and skipped, so the block itself always checks
+    fn check(self, context: &mut Context<'a>) -> Result<Self::Typed, SemanticError> {
+        let mut statements = Vec::new();
+        for statement in self {
+            let result = statement.check(context);
+            statements.extend(context.recover(result).flatten());
+        }
+        Ok(statements)
+    }
+}
+
+impl<'a> Checkable<'a> for Vec<Variable<'a>> {
+    type Typed = Vec<Initialization>;
+
+    /// Failed variables are recorded and skipped, so the ones after them are still defined
+    fn check(self, context: &mut Context<'a>) -> Result<Self::Typed, SemanticError> {
+        let mut initializations = Vec::new();
+        for variable in self {
+            let result = variable.check(context);
+            initializations.extend(context.recover(result).flatten());
+        }
+        Ok(initializations)
+    }
+}
+
+impl<'a> Checkable<'a> for Variable<'a> {
+    /// Top-level and static variables are placed into data, so only the local ones are initialized
+    type Typed = Option<Initialization>;
+
+    fn check(self, context: &mut Context<'a>) -> Result<Self::Typed, SemanticError> {
+        let (Compound(primitive, size), qualifiers) =
+            dimensioned(context, self.datatype, self.extent);
+        let value = match self.assignment.map(|x| x.value) {
+            // Character arrays take the bytes of a string, along with its terminator if it fits
+            Some(Initializer::Expression(Expression::Value(Value::String(mut bytes), string)))
+                if primitive == Primitive::Byte && size != 1 =>
+            {
+                if size == 0 {
+                    bytes.push(0);
+                } else {
+                    if bytes.len() > size {
+                        context.errors.push(SemanticError {
+                            code: "E0408",
+                            message: format!("Initializer string for '{}' is too long!", self.name),
+                            span: Some(string),
+                            notes: vec![format!(
+                                "The array holds {} bytes, but the string has {}",
+                                size,
+                                bytes.len()
+                            )],
+                            labels: vec![Label {
+                                span: self.span,
+                                message: format!("'{}' is declared here", self.name),
+                            }],
+                        });
+                    }
+                    bytes.resize(size, 0);
+                }
+
+                let entries = bytes
+                    .into_iter()
+                    .map(|x| Entry {
+                        index: None,
+                        value: Expression::Value(Value::Data(Data::Byte(x as i8)), string),
+                    })
+                    .collect();
+                Some(Initializer::List(entries))
+            }
+            value => value,
+        };
+
+        // Arrays declared without a size take it from their initialization list.
+        // Variables with errors are defined regardless, so their uses do not cause follow-up errors
+        let list = matches!(value, Some(Initializer::List(_)));
+        let elements = match value {
+            Some(Initializer::List(entries)) => {
+                Some(layout(context, self.name, self.span, entries, size))
+            }
+            Some(Initializer::Expression(value)) => Some(vec![Some(value)]),
+            None => None,
+        };
+        let size = match (&elements, size) {
+            (Some(elements), 0) if list && !elements.is_empty() => elements.len(),
+            (_, 0) => {
+                context.errors.push(SemanticError {
+                    code: "E0409",
+                    message: format!("Size of array '{}' cannot be inferred!", self.name),
+                    span: Some(self.span),
+                    notes: vec![match list {
+                        true => "The initialization list has no values".to_owned(),
+                        false => "Arrays without a size need an initialization list".to_owned(),
+                    }],
+                    ..Default::default()
+                });
+                1
+            }
+            (Some(elements), size) if !list && size > 1 => {
+                context.errors.push(SemanticError {
+                    code: "E0422",
+                    message: format!("Array '{}' must be initialized with a list!", self.name),
+                    span: elements[0].as_ref().map(|x| x.span()),
+                    ..Default::default()
+                });
+                size
+            }
+            (_, size) => size,
+        };
+        let datatype = Datatype::Type(Compound(primitive, size), qualifiers);
+
+        // External variables are defined by another object, unless they are initialized here
+        let mut storage = self.storage;
+        let mut elements = elements;
+        if storage == Some(Storage::Extern) && elements.is_some() {
+            match context.toplevel() {
+                true => storage = None,
+                false => {
+                    context.errors.push(SemanticError {
+                        code: "E0428",
+                        message: format!(
+                            "Local external variable '{}' cannot be initialized!",
+                            self.name
+                        ),
+                        span: Some(self.span),
+                        notes: vec!["External variables are defined by another object".to_owned()],
+                        ..Default::default()
+                    });
+                    elements = None;
+                }
+            }
+        }
+
+        if context.toplevel() || storage.is_some() {
+            // Top-level variables without a value are tentative, the static ones are zero-filled
+            let Some(elements) = elements else {
+                let value = match context.toplevel() || storage == Some(Storage::Extern) {
+                    true => None,
+                    false => Some(Vec::new()),
+                };
+                context.define_variable(self.name, self.span, datatype, storage, value)?;
+                return Ok(None);
+            };
+
+            // Values are evaluated ahead of time in the type of the variable,
+            // trailing elements without values are zero-filled by the assembler
+            let mut data = Vec::new();
+            for element in elements {
+                let Some(value) = element else {
+                    data.push(Data::zero(primitive));
+                    continue;
+                };
+
+                // Values with errors are already reported, so they are not evaluated
+                let errors = context.errors.len();
+                let value = value.check(context);
+                let value = context.recover(value).map(|x| cast(context, x, primitive));
+                let value = match value {
+                    Some(value) if context.errors.len() == errors => value.fold(Some(context)),
+                    _ => Ok(Data::zero(primitive)),
+                };
+                data.push(match value {
+                    Ok(value) => constant::convert(value, primitive),
+                    Err(mut error) => {
+                        let kind = if context.toplevel() {
+                            "Top-level"
+                        } else {
+                            "Static"
+                        };
+                        error.notes.push(format!(
+                            "{} variable '{}' is initialized before the program runs",
+                            kind, self.name
+                        ));
+                        context.errors.push(error);
+                        Data::zero(primitive)
+                    }
+                });
+            }
+
+            context.define_variable(self.name, self.span, datatype, storage, Some(data))?;
+            return Ok(None);
+        }
+
+        context.define_variable(self.name, self.span, datatype, None, None)?;
+        let Some(elements) = elements else {
+            return Ok(None);
+        };
+
+        // The variable is defined right before, and its initialization does not count as a use
+        let symbol = Symbol {
+            identifier: context.local_name(self.name),
+            datatype: Compound(primitive, size),
+            global: false,
+            qualifiers,
+        };
+        // Elements without values are zero-filled, so none of them are left uninitialized
+        let mut elements = elements.into_iter();
+        let mut values = Vec::new();
+        for _ in 0..size {
+            values.push(match elements.next().flatten() {
+                Some(value) => {
+                    let value = value.check(context)?;
+                    cast(context, value, primitive)
+                }
+                None => typed::Expression {
+                    kind: Kind::Data(Data::zero(primitive)),
+                    datatype: primitive,
+                    span: self.span,
+                },
+            });
+        }
+        Ok(Some(Initialization { symbol, values }))
+    }
+}
+
+/// Largest array in bytes, which keeps the sizes from overflowing
+/// and the elements few enough to be filled with zeros one by one
+const MAXIMUM_SIZE: usize = 1 << 24;
+
+/// Type of the declarator, which is an array of the declared type when it has an extent.
+/// Sizes with errors are reported, and the declarator is given a single element instead
+fn dimensioned<'a>(
+    context: &mut Context<'a>,
+    datatype: Datatype<'a>,
+    extent: Option<Extent<'a>>,
+) -> (Compound, Qualifiers) {
+    let (Compound(primitive, size), qualifiers) = context.resolve_type(datatype);
+    let size = match extent {
+        None => size,
+        Some(Extent::Inferred) => 0,
+        Some(Extent::Size(expression)) => {
+            let span = expression.span();
+            let message = "Array size must be an integer constant expression!";
+            let size = expression
+                .check(context)
+                .and_then(|x| integer(x, Primitive::Long, "E0430", message));
+            let Some(size) = context.recover(size) else {
+                return (Compound(primitive, 1), qualifiers);
+            };
+            let bytes = (size as usize).checked_mul(primitive.size());
+            if size > 0 && bytes.is_some_and(|x| x <= MAXIMUM_SIZE) {
+                return (Compound(primitive, size as usize), qualifiers);
+            }
+            let note = match size {
+                ..=0 => format!("Arrays need at least one element, but the size is {}", size),
+                _ => format!(
+                    "Arrays are limited to {} bytes, but this one has {} elements of {} bytes",
+                    MAXIMUM_SIZE,
+                    size,
+                    primitive.size()
+                ),
+            };
+            context.errors.push(SemanticError {
+                code: "E0430",
+                message: message.to_owned(),
+                span: Some(span),
+                notes: vec![note],
+                ..Default::default()
+            });
+            1
+        }
+    };
+    (Compound(primitive, size), qualifiers)
+}
+
+/// Places the values of the list at their indices, the gaps between them are left empty.
+/// Arrays without a size take any index, the others report the values past their end
+fn layout<'a>(
+    context: &mut Context<'a>,
+    name: &'a str,
+    span: Span,
+    entries: Vec<Entry<'a>>,
+    size: usize,
+) -> Vec<Option<Expression<'a>>> {
+    let mut elements: Vec<Option<Expression<'a>>> = Vec::new();
+    let mut position = 0;
+    let mut overflow = false;
+
+    for Entry { index, value } in entries {
+        if let Some(index) = index {
+            let index_span = index.span();
+            let index = index.check(context);
+            let Some(index) = context.recover(index) else {
+                continue;
+            };
+            let message = "Designator must be an integer constant!";
+            match integer(index, Primitive::Long, "E0425", message) {
+                Ok(constant) if constant >= 0 && (size == 0 || constant < size as i64) => {
+                    position = constant as usize;
+                }
+                Ok(constant) => {
+                    context.errors.push(SemanticError {
+                        code: "E0424",
+                        message: format!("Index {} is out of bounds of '{}'!", constant, name),
+                        span: Some(index_span),
+                        labels: vec![Label {
+                            span,
+                            message: format!("'{}' is declared here with {} elements", name, size),
+                        }],
+                        ..Default::default()
+                    });
+                    continue;
+                }
+                Err(error) => {
+                    context.errors.push(error);
+                    continue;
+                }
+            }
+        }
+
+        // Only the first value past the end is reported, the others follow from it
+        if size != 0 && position >= size {
+            if !overflow {
+                context.errors.push(SemanticError {
+                    code: "E0421",
+                    message: format!("Initialization list for '{}' is too long!", name),
+                    span: Some(value.span()),
+                    notes: vec![match size {
+                        1 => "Scalars take a single value".to_owned(),
+                        _ => format!(
+                            "'{}' holds {} values, so its last index is {}",
+                            name,
+                            size,
+                            size - 1
+                        ),
+                    }],
+                    labels: vec![Label {
+                        span,
+                        message: format!("'{}' is declared here", name),
+                    }],
+                });
+            }
+            overflow = true;
+            continue;
+        }
+
+        if position >= elements.len() {
+            elements.resize_with(position + 1, || None);
+        }
+        elements[position] = Some(value);
+        position += 1;
+    }
+    elements
+}
+
+impl<'a> Checkable<'a> for Loop<'a> {
+    /// Loops with errors in their conditions or increments are left out
+    type Typed = Option<typed::Loop<'a>>;
+
+    fn check(self, context: &mut Context<'a>) -> Result<Self::Typed, SemanticError> {
+        if context.toplevel() {
+            return Err(SemanticError {
+                code: "E0406",
+                message: "Loops are not allowed on the top-level!".to_owned(),
+                span: Some(self.condition.span()),
+                ..Default::default()
+            });
+        }
+        context.push_scope();
+
+        // Errors in the header are recovered from, so the scope is always popped
+        let initialization = self.initialization.check(context);
+        let initialization = context.recover(initialization).flatten().map(Box::new);
+        let condition = self.condition.check(context).map(truth);
+        let condition = context.recover(condition);
+
+        context.push_break();
+        let body = self.body.check(context)?;
+        context.pop_break();
+        let increment = self.increment.check(context);
+        let increment = context.recover(increment);
+        context.pop_scope();
+
+        let (Some(condition), Some(increment)) = (condition, increment) else {
+            return Ok(None);
+        };
+        Ok(Some(typed::Loop {
+            initialization,
+            condition,
+            increment,
+            body,
+        }))
+    }
+}
+
+impl<'a> Checkable<'a> for Switch<'a> {
+    type Typed = typed::Switch<'a>;
+
+    fn check(self, context: &mut Context<'a>) -> Result<Self::Typed, SemanticError> {
+        let span = Some(self.value.span());
+        if context.toplevel() {
+            return Err(SemanticError {
+                code: "E0406",
+                message: "Switches are not allowed on the top-level!".to_owned(),
+                span,
+                ..Default::default()
+            });
+        }
+
+        let value = self.value.check(context)?;
+        let datatype = match value.datatype {
+            Primitive::Long => Primitive::Long,
+            Primitive::Float => {
+                context.errors.push(SemanticError {
+                    code: "E0411",
+                    message: "Switch value must be an integer!".to_owned(),
+                    span,
+                    ..Default::default()
+                });
+                Primitive::Int
+            }
+            _ => Primitive::Int,
+        };
+        // Float values are reported above, so they are not warned about again
+        let value = convert(value, datatype);
+
+        context.push_scope();
+        context.push_break();
+        context.push_switch(datatype);
+        let body = self.body.check(context);
+        context.pop_switch();
+        context.pop_break();
+        context.pop_scope();
+        let body = body?;
+
+        Ok(typed::Switch { value, body })
+    }
+}
+
+/// Case or default label of the innermost switch, cases that are nested in loops belong to it too
+fn label<'a>(
+    context: &mut Context<'a>,
+    case: Case<'a>,
+    span: Span,
+) -> Result<Option<typed::Statement<'a>>, SemanticError> {
+    let name = if case.value.is_some() {
+        "case"
+    } else {
+        "default"
+    };
+    let outside = || SemanticError {
+        code: "E0404",
+        message: format!("'{}' label is not within a switch statement!", name),
+        span: Some(span),
+        ..Default::default()
+    };
+
+    let Some(expression) = case.value else {
+        let switch = context.switch().ok_or_else(outside)?;
+        if let Some(previous) = switch.default {
+            return Err(SemanticError {
+                code: "E0412",
+                message: "Switch cannot have multiple 'default' labels!".to_owned(),
+                span: Some(span),
+                labels: vec![Label {
+                    span: previous,
+                    message: "Previous 'default' label is here".to_owned(),
+                }],
+                ..Default::default()
+            });
+        }
+        switch.default = Some(span);
+        return Ok(Some(typed::Statement::Case(None, span)));
+    };
+
+    // Cases are converted to the type of the value, so they wrap around the same way
+    let datatype = context.switch().ok_or_else(outside)?.datatype;
+    let expression = expression.check(context)?;
+    let message = "Case value must be an integer constant!";
+    let constant = integer(expression, datatype, "E0413", message)?;
+
+    let switch = context.switch().ok_or_else(outside)?;
+    if let Some(&(_, previous)) = switch.values.iter().find(|(x, _)| *x == constant) {
+        return Err(SemanticError {
+            code: "E0414",
+            message: format!("Duplicate case value '{}' in switch!", constant),
+            span: Some(span),
+            labels: vec![Label {
+                span: previous,
+                message: "Previous case with the same value is here".to_owned(),
+            }],
+            ..Default::default()
+        });
+    }
+    switch.values.push((constant, span));
+    Ok(Some(typed::Statement::Case(Some(constant), span)))
+}
+
+/// Resolves the function's labels ahead of time, so `goto` can refer to them forward
+fn check_jumps<'a>(context: &mut Context<'a>, body: &[Statement<'a>]) {
+    let mut labels = Vec::new();
+    let mut gotos = Vec::new();
+    collect_jumps(body, &mut Vec::new(), &mut labels, &mut gotos);
+
+    for (index, &(name, span, _)) in labels.iter().enumerate() {
+        if let Some(&(_, previous, _)) = labels[..index].iter().find(|&&(x, ..)| x == name) {
+            context.errors.push(SemanticError {
+                code: "E0415",
+                message: format!("Label '{}' is already defined in this function!", name),
+                span: Some(span),
+                labels: vec![Label {
+                    span: previous,
+                    message: "Previously defined here".to_owned(),
+                }],
+                ..Default::default()
+            });
+        }
+    }
+
+    for (name, span, outer) in gotos {
+        let Some((.., inner)) = labels.iter().find(|&&(x, ..)| x == name) else {
+            context.errors.push(SemanticError {
+                code: "E0416",
+                message: format!("Label '{}' is not defined!", name),
+                span: Some(span),
+                ..Default::default()
+            });
+            continue;
+        };
+
+        // Declarations are told apart by their spans as well, not only by their names
+        let skipped = inner.iter().find(|x| !outer.contains(x));
+        if let Some(&(variable, declaration)) = skipped {
+            context.warn(Warning {
+                category: Category::JumpMissesInit,
+                message: format!(
+                    "Jump to label '{}' enters the scope of variable '{}'!",
+                    name, variable
+                ),
+                span: Some(span),
+                labels: vec![Label {
+                    span: declaration,
+                    message: format!("'{}' is declared here", variable),
+                }],
+                notes: Vec::new(),
+            });
+        }
+    }
+}
+
+/// Declared variable along with the span of its name
+type Declaration<'a> = (&'a str, Span);
+/// Label or goto along with the variables that are in scope at it
+type Jump<'a> = (&'a str, Span, Vec<Declaration<'a>>);
+
+/// Gathers labels and gotos along with the variables that are in scope at them
+fn collect_jumps<'a>(
+    body: &[Statement<'a>],
+    scope: &mut Vec<Declaration<'a>>,
+    labels: &mut Vec<Jump<'a>>,
+    gotos: &mut Vec<Jump<'a>>,
+) {
+    let depth = scope.len();
+    for statement in body {
+        match statement {
+            // Static and external variables are not initialized where they are declared
+            Statement::Variables(variables, _) => scope.extend(
+                variables
+                    .iter()
+                    .filter(|x| x.storage.is_none())
+                    .map(|x| (x.name, x.span)),
+            ),
+            Statement::Label(name, span) => labels.push((name, *span, scope.clone())),
+            Statement::Goto(name, span) => gotos.push((name, *span, scope.clone())),
+            Statement::Loop(repetition, _) => {
+                let outer = scope.len();
+                if let Statement::Variables(variables, _) = &*repetition.initialization {
+                    scope.extend(variables.iter().map(|x| (x.name, x.span)));
+                }
+                collect_jumps(&repetition.body, scope, labels, gotos);
+                scope.truncate(outer);
+            }
+            Statement::Switch(selection, _) => collect_jumps(&selection.body, scope, labels, gotos),
+            _ => (),
+        }
+    }
+    scope.truncate(depth);
+}
+
+impl<'a> Checkable<'a> for Expression<'a> {
+    type Typed = typed::Expression;
+
+    fn check(self, context: &mut Context<'a>) -> Result<Self::Typed, SemanticError> {
+        let span = self.span();
+        let (kind, datatype) = match self {
+            Self::Value(Value::Data(data), _) => (Kind::Data(data), data.datatype()),
+            // Strings are placed into read-only data and evaluate to their address
+            Self::Value(Value::String(bytes), _) => (Kind::String(bytes), Primitive::Long),
+            Self::Value(Value::Pointer(lvalue), _) => {
+                let place = lvalue.check(context)?;
+                let datatype = place.datatype();
+                (Kind::Load(place), datatype)
+            }
+            Self::Assignment {
+                op, target, value, ..
+            } => {
+                let target_span = target.span();
+                let target = target.check(context)?;
+                writable(context, &target, target_span)?;
+                let value = value.check(context)?;
+                let datatype = target.datatype();
+
+                let value = match op {
+                    None => cast(context, value, datatype),
+                    // Compound assignments are done in the wider of the types
+                    Some(op) => {
+                        integral(op, (datatype, target_span), (value.datatype, value.span))?;
+                        let upcast = max(datatype, value.datatype);
+                        truncation(context, upcast, datatype, span);
+                        convert(value, upcast)
+                    }
+                };
+                let value = Box::new(value);
+                (Kind::Assignment { op, target, value }, datatype)
+            }
+            Self::Update {
+                op,
+                target,
+                postfix,
+                ..
+            } => {
+                let target_span = target.span();
+                let target = target.check(context)?;
+                writable(context, &target, target_span)?;
+                let datatype = target.datatype();
+                (
+                    Kind::Update {
+                        op,
+                        target,
+                        postfix,
+                    },
+                    datatype,
+                )
+            }
+            Self::Conditional {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                let condition = truth(condition.check(context)?);
+                let then = then.check(context)?;
+                let otherwise = otherwise.check(context)?;
+                let upcast = max(then.datatype, otherwise.datatype);
+                (
+                    Kind::Conditional {
+                        condition: Box::new(condition),
+                        then: Box::new(convert(then, upcast)),
+                        otherwise: Box::new(convert(otherwise, upcast)),
+                    },
+                    upcast,
+                )
+            }
+            Self::Comma { lhs, rhs, .. } => {
+                let lhs = lhs.check(context)?;
+                let rhs = rhs.check(context)?;
+                let datatype = rhs.datatype;
+                let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+                (Kind::Comma { lhs, rhs }, datatype)
+            }
+            Self::Cast {
+                datatype,
+                value,
+                span,
+            } => {
+                let (Compound(primitive, count), _) = context.resolve_type(datatype);
+                if count > 1 {
+                    return Err(SemanticError {
+                        code: "E0417",
+                        message: "Cannot cast to an array type!".to_owned(),
+                        span: Some(span),
+                        ..Default::default()
+                    });
+                }
+
+                let value = value.check(context)?;
+                let value = convert(value, primitive);
+                return Ok(typed::Expression { span, ..value });
+            }
+            Self::Sizeof(operand, _) => {
+                let size = match operand {
+                    Sizeof::Type(datatype) => context.resolve_type(datatype).0.size(),
+                    Sizeof::Expression(expression) => size(context, *expression)?,
+                };
+                (Kind::Data(Data::Long(size as i64)), Primitive::Long)
+            }
+            Self::Unary { op, lhs, .. } => {
+                let lhs = lhs.check(context)?;
+                let datatype = match op {
+                    UnaryOperator::Complement if lhs.datatype.floating() => {
+                        return Err(SemanticError {
+                            code: "E0418",
+                            message: "Bitwise complement requires an integer operand!".to_owned(),
+                            span: Some(lhs.span),
+                            ..Default::default()
+                        });
+                    }
+                    UnaryOperator::Inversion => Primitive::Int,
+                    _ => lhs.datatype,
+                };
+                let lhs = match op {
+                    UnaryOperator::Inversion => Box::new(truth(lhs)),
+                    _ => Box::new(lhs),
+                };
+                (Kind::Unary { op, lhs }, datatype)
+            }
+            Self::Binary { op, lhs, rhs, span } => {
+                let lhs = lhs.check(context)?;
+                let rhs = rhs.check(context)?;
+                if let Some(value) = tautology(op, &lhs, &rhs) {
+                    context.warn(Warning {
+                        category: Category::TautologicalCompare,
+                        message: format!("Comparison is always {}!", value),
+                        span: Some(span),
+                        labels: Vec::new(),
+                        notes: Vec::new(),
+                    });
+                }
+                integral(op, (lhs.datatype, lhs.span), (rhs.datatype, rhs.span))?;
+                let (lhs, rhs) = match op {
+                    BinaryOperator::And | BinaryOperator::Or => (truth(lhs), truth(rhs)),
+                    _ => (lhs, rhs),
+                };
+
+                let upcast = max(lhs.datatype, rhs.datatype);
+                let datatype = match op {
+                    BinaryOperator::Addition
+                    | BinaryOperator::Subtraction
+                    | BinaryOperator::Division
+                    | BinaryOperator::Multiplication
+                    | BinaryOperator::Remainder
+                    | BinaryOperator::BitwiseAnd
+                    | BinaryOperator::BitwiseOr
+                    | BinaryOperator::BitwiseXor
+                    | BinaryOperator::LeftShift
+                    | BinaryOperator::RightShift => upcast,
+                    _ => Primitive::Int,
+                };
+                let lhs = Box::new(convert(lhs, upcast));
+                let rhs = Box::new(convert(rhs, upcast));
+                (Kind::Binary { op, lhs, rhs }, datatype)
+            }
+        };
+
+        Ok(typed::Expression {
+            kind,
+            datatype,
+            span,
+        })
+    }
+}
+
+impl<'a> Checkable<'a> for Lvalue<'a> {
+    type Typed = Place;
+
+    fn check(self, context: &mut Context<'a>) -> Result<Self::Typed, SemanticError> {
+        let (name, index, span) = match self {
+            Lvalue::Variable(name, span) => {
+                let symbol = context.symbol(name, span);
+                // Whole arrays are only measured by `sizeof`, which does not get here
+                if symbol.datatype.1 > 1 {
+                    return Err(SemanticError {
+                        code: "E0429",
+                        message: format!("Array '{}' cannot be used without an index!", name),
+                        span: Some(span),
+                        ..Default::default()
+                    });
+                }
+                return Ok(Place::Direct(symbol, 0));
+            }
+            Lvalue::Element(name, index, span) => (name, index, span),
+        };
+
+        let symbol = context.symbol(name, span);
+        let index = index.check(context)?;
+
+        // Undefined variables are already reported, so their bounds are not checked
+        let Compound(_, size) = symbol.datatype;
+        if let Some(constant) = index.constant()
+            && (constant < 0 || constant >= size as i64)
+            && let Some(&(_, declaration)) = context.definitions.get(&symbol.identifier)
+        {
+            return Err(SemanticError {
+                code: "E0424",
+                message: format!("Index {} is out of bounds of '{}'!", constant, name),
+                span: Some(index.span),
+                labels: vec![Label {
+                    span: declaration,
+                    message: format!("'{}' is declared here with {} elements", name, size),
+                }],
+                ..Default::default()
+            });
+        }
+        if let Some(constant) = index.constant()
+            && constant >= 0
+        {
+            return Ok(Place::Direct(symbol, constant as usize));
+        }
+
+        if index.datatype.floating() {
+            return Err(SemanticError {
+                code: "E0420",
+                message: format!("Index of '{}' must be an integer!", name),
+                span: Some(index.span),
+                ..Default::default()
+            });
+        }
+        Ok(Place::Indexed(
+            symbol,
+            Box::new(convert(index, Primitive::Long)),
+        ))
+    }
+}
+
+/// Size of the expression's value in bytes, whole arrays are measured by their names.
+/// The expression is only inspected and never evaluated
+fn size<'a>(context: &mut Context<'a>, expression: Expression<'a>) -> Result<usize, SemanticError> {
+    match expression {
+        Expression::Value(Value::Pointer(Lvalue::Variable(name, span)), _) => {
+            Ok(context.symbol(name, span).datatype.size())
+        }
+        Expression::Value(Value::String(bytes), _) => Ok(bytes.len() + 1),
+        expression => Ok(expression.check(context)?.datatype.size()),
+    }
+}
+
+/// Value of the integer constant expression in the given type,
+/// otherwise the error points at the part that cannot be evaluated
+fn integer(
+    expression: typed::Expression,
+    to: Primitive,
+    code: &'static str,
+    message: &str,
+) -> Result<i64, SemanticError> {
+    let span = expression.span;
+    let value = match expression.datatype.floating() {
+        true => Err((span, Vec::new())),
+        false => convert(expression, to)
+            .fold(None)
+            .map_err(|error| (error.span.unwrap_or(span), error.notes)),
+    };
+    value
+        .map(|x| i64::from(&x))
+        .map_err(|(span, notes)| SemanticError {
+            code,
+            message: message.to_owned(),
+            span: Some(span),
+            notes,
+            ..Default::default()
+        })
+}
+
+/// Rejects assignments to the constant variables, their values are only set by their initialization
+fn writable(context: &Context, place: &Place, span: Span) -> Result<(), SemanticError> {
+    let symbol = place.symbol();
+    if !symbol.qualifiers.constant {
+        return Ok(());
+    }
+
+    let (name, declaration) = context.definitions[&symbol.identifier];
+    Err(SemanticError {
+        code: "E0427",
+        message: format!("Cannot assign to '{}' because it is constant!", name),
+        span: Some(span),
+        labels: vec![Label {
+            span: declaration,
+            message: format!("'{}' is declared constant here", name),
+        }],
+        ..Default::default()
+    })
+}
+
+/// Rejects floats as the operands of the operators that only work on integers
+fn integral(
+    op: BinaryOperator,
+    lhs: (Primitive, Span),
+    rhs: (Primitive, Span),
+) -> Result<(), SemanticError> {
+    let integral = matches!(
+        op,
+        BinaryOperator::Remainder
+            | BinaryOperator::BitwiseAnd
+            | BinaryOperator::BitwiseOr
+            | BinaryOperator::BitwiseXor
+            | BinaryOperator::LeftShift
+            | BinaryOperator::RightShift
+    );
+    match [lhs, rhs]
+        .into_iter()
+        .find(|(datatype, _)| datatype.floating())
+    {
+        Some((_, span)) if integral => Err(SemanticError {
+            code: "E0423",
+            message: format!("Operator '{}' requires integer operands!", op),
+            span: Some(span),
+            ..Default::default()
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Implicit conversion, which warns when a float is truncated to an integer
+/// or when a constant does not fit into the type
+fn cast(context: &mut Context, expression: typed::Expression, to: Primitive) -> typed::Expression {
+    truncation(context, expression.datatype, to, expression.span);
+    if let Some(value) = expression.constant()
+        && !to.floating()
+    {
+        let converted = i64::from(&constant::convert(Data::Long(value), to));
+        if converted != value {
+            context.warn(Warning {
+                category: Category::ConstantConversion,
+                message: format!(
+                    "Implicit conversion from {} to {} changes the value to {}!",
+                    value, to, converted
+                ),
+                span: Some(expression.span),
+                labels: Vec::new(),
+                notes: Vec::new(),
+            });
+        }
+    }
+    convert(expression, to)
+}
+
+/// Explicit conversion, such as the one of a cast expression
+fn convert(expression: typed::Expression, to: Primitive) -> typed::Expression {
+    if expression.datatype == to {
+        return expression;
+    }
+    typed::Expression {
+        span: expression.span,
+        kind: Kind::Conversion(Box::new(expression)),
+        datatype: to,
+    }
+}
+
+/// Integer truth value of the condition, floats are compared against zero
+/// since the integer comparison with zero cannot take a float register
+fn truth(condition: typed::Expression) -> typed::Expression {
+    if !condition.datatype.floating() {
+        return condition;
+    }
+    let span = condition.span;
+    let zero = typed::Expression {
+        kind: Kind::Data(Data::Float(0.0)),
+        datatype: Primitive::Float,
+        span,
+    };
+    typed::Expression {
+        kind: Kind::Binary {
+            op: BinaryOperator::NotEqual,
+            lhs: Box::new(condition),
+            rhs: Box::new(zero),
+        },
+        datatype: Primitive::Int,
+        span,
+    }
+}
+
+fn truncation(context: &mut Context, from: Primitive, to: Primitive, span: Span) {
+    if from.floating() && !to.floating() {
+        context.warn(Warning {
+            category: Category::FloatConversion,
+            message: format!(
+                "Implicit conversion from float to {} may lose precision!",
+                to
+            ),
+            span: Some(span),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        });
+    }
+}
+
+/// Result of the comparison that does not depend on the values of its operands
+fn tautology(op: BinaryOperator, lhs: &typed::Expression, rhs: &typed::Expression) -> Option<bool> {
+    // Floats are left out, as NaN is not equal to itself, and so are the volatile variables,
+    // as each of their reads can give a different value
+    if let (Kind::Load(Place::Direct(a, i)), Kind::Load(Place::Direct(b, j))) =
+        (&lhs.kind, &rhs.kind)
+        && a.identifier == b.identifier
+        && i == j
+        && !lhs.datatype.floating()
+        && !a.qualifiers.volatile
+    {
+        return match op {
+            BinaryOperator::Equal | BinaryOperator::GreaterEqual | BinaryOperator::LessEqual => {
+                Some(true)
+            }
+            BinaryOperator::NotEqual | BinaryOperator::Greater | BinaryOperator::Less => {
+                Some(false)
+            }
+            _ => None,
+        };
+    }
+
+    // Narrow variables cannot reach the constants out of their range
+    let (variable, constant, op) = match (lhs.constant(), rhs.constant()) {
+        (None, Some(constant)) => (lhs, constant, op),
+        (Some(constant), None) => {
+            let op = match op {
+                BinaryOperator::Greater => BinaryOperator::Less,
+                BinaryOperator::Less => BinaryOperator::Greater,
+                BinaryOperator::GreaterEqual => BinaryOperator::LessEqual,
+                BinaryOperator::LessEqual => BinaryOperator::GreaterEqual,
+                op => op,
+            };
+            (rhs, constant, op)
+        }
+        _ => return None,
+    };
+    if !matches!(variable.kind, Kind::Load(_)) {
+        return None;
+    }
+    let (min, max) = match variable.datatype {
+        Primitive::Byte => (i8::MIN as i64, i8::MAX as i64),
+        Primitive::Short => (i16::MIN as i64, i16::MAX as i64),
+        _ => return None,
+    };
+
+    let outside = constant < min || constant > max;
+    let (always, never) = match op {
+        BinaryOperator::Less => (max < constant, min >= constant),
+        BinaryOperator::LessEqual => (max <= constant, min > constant),
+        BinaryOperator::Greater => (min > constant, max <= constant),
+        BinaryOperator::GreaterEqual => (min >= constant, max < constant),
+        BinaryOperator::Equal => (false, outside),
+        BinaryOperator::NotEqual => (outside, false),
+        _ => return None,
+    };
+    match (always, never) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Data;
+    use crate::{
+        testing::{check, diagnostics},
+        ErrorLike,
+    };
+
+    fn assert_diagnostics(cases: &[(&str, &[&str])]) {
+        for (text, codes) in cases {
+            let text = format!("int main() {{ {text} }}");
+            assert_eq!(diagnostics(&text), *codes, "{text}");
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert_diagnostics(&[
+            ("return x;", &["E0401"]),
+            ("T x = 1; return x;", &["E0402"]),
+            ("int x = 1; int x = 2; return x;", &["E0403"]),
+            (
+                "int x = 1; for (int i = 0; i < 1; i++) { int x = 2; return x; } return x;",
+                &["W0003"],
+            ),
+            ("int x = 1; return 0;", &["W0002"]),
+            ("typedef int T; return 0;", &["W0006"]),
+        ]);
+        assert_eq!(diagnostics("int f() { return 0; }"), ["E0407"]);
+    }
+
+    /// Notes of the errors in the function body, where misspelled names get a suggestion
+    fn notes(text: &str) -> Vec<String> {
+        let text = format!("int main() {{ {text} }}");
+        let (context, _) = check(&text);
+        context.errors.iter().flat_map(|x| x.notes()).collect()
+    }
+
+    #[test]
+    fn suggestions() {
+        let cases: [(&str, &[&str]); 9] = [
+            ("int count = 1; return cuont;", &["Did you mean 'count'?"]),
+            ("int value = 1; return valu;", &["Did you mean 'value'?"]),
+            (
+                "int b = 1; int c = 1; return a + b + c;",
+                &["Did you mean 'b'?"],
+            ),
+            ("int apple = 1; return grape + apple;", &[]),
+            // Names of six characters are allowed two edits, but not three
+            (
+                "int abcdef = 1; return abxyef + abcdef;",
+                &["Did you mean 'abcdef'?"],
+            ),
+            ("int abcdef = 1; return axyzef + abcdef;", &[]),
+            (
+                "for (int inner = 0; inner < 1; inner++) {} return iner;",
+                &[],
+            ),
+            ("flaot x = 1.5; return 0;", &["Did you mean 'float'?"]),
+            (
+                "typedef int Length; Lenght x = 1; return x;",
+                &["Did you mean 'Length'?"],
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(notes(text), *expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn placement() {
+        assert_diagnostics(&[
+            ("case 1: return 0;", &["E0404"]),
+            ("default: return 0;", &["E0404"]),
+            ("break; return 0;", &["E0405"]),
+            ("l: l: return 0;", &["E0415"]),
+            ("goto l; return 0;", &["E0416"]),
+            ("goto l; int x = 1; l: return x;", &["W0001"]),
+            (
+                "goto l; for (int i = 0; i < 1; i++) { l: ; } return 0;",
+                &["W0001"],
+            ),
+            (
+                "int i = 0; goto l; for (i = 1; i < 2; i++) { l: ; } return i;",
+                &[],
+            ),
+            (
+                "int i; int j; for (i = 0, j = 2; i < j; i++, j--) {} return i + j;",
+                &[],
+            ),
+        ]);
+        assert_eq!(diagnostics("return 0;"), ["E0406"]);
+        assert_eq!(diagnostics("int x = 1; x = 2;"), ["E0406"]);
+        assert_eq!(diagnostics("for (int i = 0; i < 1; i++) {}"), ["E0406"]);
+    }
+
+    #[test]
+    fn switches() {
+        assert_diagnostics(&[
+            (
+                "float f = 1.0; switch (f) { default: break; } return 0;",
+                &["E0411"],
+            ),
+            (
+                "switch (1) { default: default: break; } return 0;",
+                &["E0412"],
+            ),
+            (
+                "int x = 1; switch (x) { case x: break; } return 0;",
+                &["E0413"],
+            ),
+            (
+                "switch (1) { case 1: case 2 - 1: break; } return 0;",
+                &["E0414"],
+            ),
+            (
+                "switch (1) { case 1: for (int i = 0; i < 1; i++) { case 2: break; } } return 0;",
+                &[],
+            ),
+            (
+                "switch (1) { case 1: for (int i = 0; i < 1; i++) { case 1: break; } } return 0;",
+                &["E0414"],
+            ),
+        ]);
+    }
+
+    #[test]
+    fn arrays() {
+        assert_diagnostics(&[
+            ("char s[2] = \"abc\"; return s[0];", &["E0408"]),
+            ("int a[] = {}; return 0;", &["E0409", "W0002"]),
+            ("int a[2] = {1}; float f = 1.0; return a[f];", &["E0420"]),
+            ("int a[2] = {1, 2, 3}; return a[0];", &["E0421"]),
+            ("int a[2] = 1; return a[0];", &["E0422"]),
+            ("int a[2] = {1}; return a[2];", &["E0424"]),
+            ("int a[2] = {[2] = 1}; return a[0];", &["E0424"]),
+            ("int x = 1; int a[2] = {[x] = 1}; return a[0];", &["E0425"]),
+            ("int a[4]; a = 5; return 0;", &["E0429"]),
+            ("int a[4]; int x = 1; return a + x;", &["E0429", "W0002"]),
+            ("int a[4]; return sizeof a;", &[]),
+        ]);
+    }
+
+    /// Logical operators are folded like they are evaluated, so the right side may not be constant
+    #[test]
+    fn logical_constants() {
+        let (context, _) = check(
+            "int a = 1 && 2; int b = 0 || 3; int c = 0 && 1 / 0; int d = 1 || 1 / 0; int main() { return a + b + c + d; }",
+        );
+        assert!(context.errors.is_empty());
+        let values = ["a", "b", "c", "d"].map(|x| context.globals[&context.local_name(x)].1[0]);
+        assert_eq!(values, [1, 1, 0, 1].map(Data::Integer));
+    }
+
+    #[test]
+    fn extents() {
+        assert_diagnostics(&[
+            ("int a[2 * 2] = {1}; return a[3];", &[]),
+            ("int a[2 * 2] = {1}; return a[4];", &["E0424"]),
+            ("int b[4 + 1] = {1}; return b[4];", &[]),
+            ("int c[sizeof(long) / 2] = {1}; return c[3];", &[]),
+            ("typedef int A[1 << 2]; A a = {1}; return a[4];", &["E0424"]),
+            ("int n = 2; int a[n]; return 0;", &["E0430", "W0002"]),
+            ("int a[1.5]; return 0;", &["E0430", "W0002"]),
+            ("int a[1 - 2]; return 0;", &["E0430", "W0002"]),
+            ("int a[0]; return 0;", &["E0430", "W0002"]),
+            (
+                "long a[9223372036854775807]; return 0;",
+                &["E0430", "W0002"],
+            ),
+            ("int a[4194305]; return 0;", &["E0430", "W0002"]),
+            ("char a[16777216]; return 0;", &["W0002"]),
+        ]);
+    }
+
+    #[test]
+    fn types() {
+        assert_diagnostics(&[
+            ("typedef int A[2]; return (A) 1;", &["E0417"]),
+            ("return ~1.5;", &["E0418"]),
+            ("return 1.5 % 2;", &["E0423"]),
+            ("int x = 1; x <<= 1.5; return x;", &["E0423"]),
+            ("const int c = 1; c = 2; return c;", &["E0427"]),
+            ("const int c = 1; c++; return c;", &["E0427"]),
+            ("extern int e = 1; return 0;", &["E0428"]),
+            ("int x = 1.5; return x;", &["W0004"]),
+            ("int x = 1; return x == x;", &["W0005"]),
+            ("char c = 300; return c;", &["W0007"]),
+            ("char c = 0; c = -129; return c;", &["W0007"]),
+            ("char c = -128; short s = 32767; return c + s;", &[]),
+        ]);
+        assert_eq!(
+            diagnostics("int y = 1; int g = y; int main() { return g; }"),
+            ["E0426"]
+        );
+        assert_eq!(
+            diagnostics("int g = 1 / 0; int main() { return g; }"),
+            ["E0426"]
+        );
+    }
+}
@@ -1,17 +1,25 @@
 use super::ErrorLike;
+use crate::Span;
 
 #[derive(Clone)]
 pub struct AssemblyError {
     pub message: String,
+    /// Span of the code that the failing instruction was translated from
+    pub span: Option<Span>,
 }
 
-impl<'a> ErrorLike for AssemblyError {
+impl ErrorLike for AssemblyError {
     fn kind() -> &'static str {
-        return "AssemblyError";
+        "AssemblyError"
     }
 
-    fn slice(&self) -> Option<&str> {
-        None
+    /// Assembly errors are internal ones, as they come from the compiler's own output
+    fn code(&self) -> &'static str {
+        "E0501"
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
     }
 
     fn message(&self) -> String {
@@ -1,16 +1,21 @@
 use super::ErrorLike;
+use crate::Span;
 
 #[derive(Clone)]
 pub struct CompileError {
     pub message: &'static str,
 }
 
-impl<'a> ErrorLike for CompileError {
+impl ErrorLike for CompileError {
     fn kind() -> &'static str {
-        return "CompileError";
+        "CompileError"
     }
 
-    fn slice(&self) -> Option<&str> {
+    fn code(&self) -> &'static str {
+        "E0601"
+    }
+
+    fn span(&self) -> Option<Span> {
         None
     }
 
@@ -0,0 +1,317 @@
+use super::lookup;
+use crate::{preprocessor::Source, Span};
+use colored::{Color, Colorize};
+use std::cmp::{max, min};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// How diagnostics are printed: `pretty` for people, `json` for tools, one object per line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Pretty,
+    Json,
+}
+
+/// Secondary span that explains how it relates to the primary one
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: &'static str,
+    /// Stable identifier of the problem: `E01..` preprocessing, `E02..` lexing, `E03..` syntax,
+    /// `E04..` semantics, `E05..` assembly, `E06..` toolchain and `W00..` warnings
+    pub code: &'static str,
+    pub message: String,
+    /// Missing for the failures of the tools, which are printed without a snippet
+    /// and have a null span in JSON
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Severity {
+    fn color(&self) -> Color {
+        match self {
+            Self::Error => Color::Red,
+            Self::Warning => Color::Yellow,
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn render(&self, source: &Source, format: Format) -> String {
+        match format {
+            Format::Pretty => self.pretty(source),
+            Format::Json => self.json(source),
+        }
+    }
+
+    /// Prints the diagnostic, tools get it on the standard error without the other output
+    pub fn emit(&self, source: &Source, format: Format) {
+        match format {
+            Format::Pretty => println!("{}\n", self.render(source, format)),
+            Format::Json => eprintln!("{}", self.render(source, format)),
+        }
+    }
+
+    fn pretty(&self, source: &Source) -> String {
+        let color = self.severity.color();
+        let mut text = format!(
+            "{}{} {}",
+            format!("{}[{}]", self.kind, self.code).color(color).bold(),
+            ":".color(color).bold(),
+            self.message.color(color),
+        );
+        // Failures of the tools have no code to point at
+        if let Some(span) = self.span {
+            text += &format!("\n{}", snippet(source, span, color));
+        }
+
+        for label in &self.labels {
+            text += &format!(
+                "\n{} {}\n{}",
+                "note:".blue().bold(),
+                label.message,
+                snippet(source, label.span, Color::Blue)
+            );
+        }
+        for note in &self.notes {
+            text += &format!("\n   {} {}", "= note:".blue().bold(), note);
+        }
+        text
+    }
+
+    fn json(&self, source: &Source) -> String {
+        let labels: Vec<_> = self
+            .labels
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"message\":{},\"span\":{}}}",
+                    escape(&label.message),
+                    location(source, Some(label.span))
+                )
+            })
+            .collect();
+        let notes: Vec<_> = self.notes.iter().map(|x| escape(x)).collect();
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        format!(
+            "{{\"severity\":\"{}\",\"kind\":\"{}\",\"code\":\"{}\",\"message\":{},\"span\":{},\"labels\":[{}],\"notes\":[{}]}}",
+            severity,
+            self.kind,
+            self.code,
+            escape(&self.message),
+            location(source, self.span),
+            labels.join(","),
+            notes.join(",")
+        )
+    }
+}
+
+/// Location of the span along with the surrounding lines, where the span is highlighted
+fn snippet(source: &Source, span: Span, color: Color) -> String {
+    let (filename, code) = source.file(span.file);
+    let (start, end) = (span.start, span.end);
+    let (line, char) = lookup(code, start);
+    let pad = (line + 2).to_string().len() + 2;
+    let line_end = code[start..].find('\n').map_or(code.len(), |x| start + x);
+    let length = min(end, line_end) - start;
+
+    let snippet = code
+        .lines()
+        .enumerate()
+        .skip(max(line as i64 - 1, 0_i64).try_into().unwrap())
+        .take(3)
+        .map(|(n, text)| {
+            format!(
+                "{: >pad$} {} {}",
+                (n + 1).to_string().blue().bold(),
+                "|".blue().bold(),
+                if n == line {
+                    let before = &text[..char].underline();
+                    let highlighted = &text[char..char + length].color(color).bold().underline();
+                    let after = &text[char + length..].underline();
+                    format!("{}{}{}", before, highlighted, after)
+                } else {
+                    text.to_owned()
+                },
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let spacer_pad = pad + 3;
+    format!(
+        "   {: >pad$} {}:{}:{}\n{: >spacer_pad$}\n{}",
+        "-->".blue().bold(),
+        filename.cyan(),
+        (line + 1).to_string().cyan(),
+        (char + 1).to_string().cyan(),
+        "| ".blue().bold(),
+        snippet
+    )
+}
+
+/// JSON object with the file, byte offsets and one-based lines and columns of the span
+fn location(source: &Source, span: Option<Span>) -> String {
+    let Some(span) = span else {
+        return "null".to_owned();
+    };
+    let (filename, code) = source.file(span.file);
+    let (line, column) = lookup(code, span.start);
+    let (end_line, end_column) = lookup(code, span.end);
+
+    format!(
+        "{{\"file\":{},\"start\":{},\"end\":{},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}}}",
+        escape(filename),
+        span.start,
+        span.end,
+        line + 1,
+        column + 1,
+        end_line + 1,
+        end_column + 1
+    )
+}
+
+/// Quoted JSON string
+fn escape(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for char in text.chars() {
+        match char {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            '\t' => escaped += "\\t",
+            '\r' => escaped += "\\r",
+            x if x.is_control() => escaped += &format!("\\u{:04x}", x as u32),
+            x => escaped.push(x),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessor::Preprocessor;
+
+    fn source(text: &str) -> Source {
+        let mut preprocessor = Preprocessor::new(Vec::new(), Vec::new());
+        assert!(preprocessor.include("main.c", text.to_owned()).is_ok());
+        preprocessor.finish()
+    }
+
+    fn diagnostic(span: Option<Span>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            kind: "SemanticError",
+            code: "E0401",
+            message: "Variable 'x' is not defined!".to_owned(),
+            span,
+            labels: Vec::new(),
+            notes: vec!["Did you mean 'y'?".to_owned()],
+        }
+    }
+
+    #[test]
+    fn pretty() {
+        colored::control::set_override(false);
+        let source = source("int main() {\n  return x;\n}\n");
+        let span = Span {
+            file: 0,
+            start: 22,
+            end: 23,
+        };
+
+        let text = diagnostic(Some(span)).render(&source, Format::Pretty);
+        let expected = [
+            "SemanticError[E0401]: Variable 'x' is not defined!",
+            "   --> main.c:2:10",
+            "    | ",
+            "  1 | int main() {",
+            "  2 |   return x;",
+            "  3 | }",
+            "   = note: Did you mean 'y'?",
+        ];
+        assert_eq!(text.lines().collect::<Vec<_>>(), expected);
+
+        // Failures of the tools only have their message and notes
+        let text = diagnostic(None).render(&source, Format::Pretty);
+        let expected = [
+            "SemanticError[E0401]: Variable 'x' is not defined!",
+            "   = note: Did you mean 'y'?",
+        ];
+        assert_eq!(text.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn json() {
+        let source = source("int main() {\n  return x;\n}\n");
+        let span = Span {
+            file: 0,
+            start: 22,
+            end: 23,
+        };
+        let mut error = diagnostic(Some(span));
+        error.labels.push(Label {
+            span: Span {
+                file: 0,
+                start: 4,
+                end: 8,
+            },
+            message: "in \"main\"".to_owned(),
+        });
+
+        let text = error.render(&source, Format::Json);
+        assert_eq!(
+            text,
+            concat!(
+                r#"{"severity":"error","kind":"SemanticError","code":"E0401","#,
+                r#""message":"Variable 'x' is not defined!","#,
+                r#""span":{"file":"main.c","start":22,"end":23,"line":2,"column":10,"end_line":2,"end_column":11},"#,
+                r#""labels":[{"message":"in \"main\"","#,
+                r#""span":{"file":"main.c","start":4,"end":8,"line":1,"column":5,"end_line":1,"end_column":9}}],"#,
+                r#""notes":["Did you mean 'y'?"]}"#
+            )
+        );
+
+        let mut warning = diagnostic(None);
+        warning.severity = Severity::Warning;
+        warning.notes.clear();
+        let text = warning.render(&source, Format::Json);
+        assert!(text.contains(r#""severity":"warning""#), "{text}");
+        assert!(
+            text.contains(r#""span":null,"labels":[],"notes":[]}"#),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        let cases = [
+            ("plain", r#""plain""#),
+            ("say \"hi\"", r#""say \"hi\"""#),
+            ("C:\\path", r#""C:\\path""#),
+            ("a\nb\tc\rd", r#""a\nb\tc\rd""#),
+            ("\u{0}\u{1b}\u{7f}", r#""\u0000\u001b\u007f""#),
+            ("ünïcode", r#""ünïcode""#),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(escape(text), expected, "{text:?}");
+        }
+    }
+}
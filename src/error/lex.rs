@@ -0,0 +1,27 @@
+use super::ErrorLike;
+use crate::Span;
+
+#[derive(Clone)]
+pub struct LexError {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl ErrorLike for LexError {
+    fn kind() -> &'static str {
+        "LexError"
+    }
+
+    fn code(&self) -> &'static str {
+        self.code
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    fn message(&self) -> String {
+        self.message.clone()
+    }
+}
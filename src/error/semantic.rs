@@ -1,21 +1,37 @@
-use super::ErrorLike;
+use super::{diagnostic::Label, ErrorLike};
+use crate::Span;
 
-#[derive(Clone)]
-pub struct SemanticError<'a> {
+#[derive(Clone, Default)]
+pub struct SemanticError {
+    pub code: &'static str,
     pub message: String,
-    pub token: Option<&'a str>,
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
 }
 
-impl<'a> ErrorLike for SemanticError<'a> {
+impl ErrorLike for SemanticError {
     fn kind() -> &'static str {
-        return "SemanticError";
+        "SemanticError"
     }
 
-    fn slice(&self) -> Option<&'a str> {
-        self.token
+    fn code(&self) -> &'static str {
+        self.code
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
     }
 
     fn message(&self) -> String {
         self.message.clone()
     }
+
+    fn labels(&self) -> Vec<Label> {
+        self.labels.clone()
+    }
+
+    fn notes(&self) -> Vec<String> {
+        self.notes.clone()
+    }
 }
@@ -1,7 +1,7 @@
 use super::ErrorLike;
-use crate::Token;
+use crate::{Span, Token, UNSUPPORTED};
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct SyntaxError<'a> {
     pub expected: String,
     pub found: Option<Token<'a>>,
@@ -9,39 +9,59 @@ pub struct SyntaxError<'a> {
 
 impl<'a> ErrorLike for SyntaxError<'a> {
     fn kind() -> &'static str {
-        return "SyntaxError";
+        "SyntaxError"
     }
 
-    fn lookup(text: &str, slice: Option<&str>) -> (usize, usize) {
-        let offset = match slice {
-            Some(slice) => slice.as_ptr() as usize - text.as_ptr() as usize,
-            None => text.len(),
-        };
-
-        let line = text[..(offset + 1).min(text.len())].lines().count();
-        let start = text[..offset]
-            .char_indices()
-            .rev()
-            .find(|x| x.1 == '\n')
-            .and_then(|x| Some(x.0 as i64))
-            .unwrap_or(-1);
-
-        return (line - 1, (offset as i64 - start - 1) as usize);
-    }
-
-    fn slice(&self) -> Option<&'a str> {
+    fn code(&self) -> &'static str {
         match self.found {
-            Some(
-                Token::Identifier(x) | Token::Keyword(x) | Token::Symbol(x) | Token::Data(_, x),
-            ) => Some(x),
-            _ => None,
+            Some(_) => "E0301",
+            None => "E0302",
         }
     }
 
+    fn span(&self) -> Option<Span> {
+        self.found.map(|x| x.span())
+    }
+
     fn message(&self) -> String {
-        match self.slice() {
-            Some(x) => format!("Expected {}, but found {}!", self.expected, x),
+        match self.found {
+            Some(x) => format!("Expected {}, but found {}!", self.expected, x.text()),
             _ => format!("Unexpected end of file! (expected {})", self.expected),
         }
     }
+
+    fn notes(&self) -> Vec<String> {
+        match self.found {
+            Some(Token::Keyword(x, _)) if UNSUPPORTED.contains(&x) => {
+                vec![format!("'{}' is a C keyword that is not supported yet", x)]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::parse, ErrorLike};
+
+    #[test]
+    fn unsupported() {
+        let cases: [(&str, &[&str]); 4] = [
+            (
+                "int main() { while (1) {} return 0; }",
+                &["'while' is a C keyword that is not supported yet"],
+            ),
+            (
+                "int main() { unsigned x = 1; return x; }",
+                &["'unsigned' is a C keyword that is not supported yet"],
+            ),
+            ("int main() { int = 5; return 0; }", &[]),
+            ("int main() { return 0;", &[]),
+        ];
+        for (text, expected) in cases {
+            let (_, errors) = parse(text);
+            let notes: Vec<_> = errors.iter().flat_map(|x| x.notes()).collect();
+            assert_eq!(notes, *expected, "{text}");
+        }
+    }
 }
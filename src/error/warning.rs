@@ -0,0 +1,103 @@
+use super::{
+    diagnostic::{Label, Severity},
+    ErrorLike,
+};
+use crate::Span;
+
+/// Kind of a warning, which is enabled with `-W<name>` and disabled with `-Wno-<name>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    JumpMissesInit,
+    UnusedVariable,
+    Shadow,
+    FloatConversion,
+    TautologicalCompare,
+    UnusedTypedef,
+    ConstantConversion,
+}
+
+impl Category {
+    pub const ALL: [Category; 7] = [
+        Category::JumpMissesInit,
+        Category::UnusedVariable,
+        Category::Shadow,
+        Category::FloatConversion,
+        Category::TautologicalCompare,
+        Category::UnusedTypedef,
+        Category::ConstantConversion,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::JumpMissesInit => "jump-misses-init",
+            Self::UnusedVariable => "unused-variable",
+            Self::Shadow => "shadow",
+            Self::FloatConversion => "float-conversion",
+            Self::TautologicalCompare => "tautological-compare",
+            Self::UnusedTypedef => "unused-typedef",
+            Self::ConstantConversion => "constant-conversion",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::JumpMissesInit => "W0001",
+            Self::UnusedVariable => "W0002",
+            Self::Shadow => "W0003",
+            Self::FloatConversion => "W0004",
+            Self::TautologicalCompare => "W0005",
+            Self::UnusedTypedef => "W0006",
+            Self::ConstantConversion => "W0007",
+        }
+    }
+
+    /// Noisy warnings are only enabled by their flags or `-Wall`
+    pub fn enabled_by_default(&self) -> bool {
+        !matches!(self, Self::Shadow | Self::FloatConversion)
+    }
+}
+
+#[derive(Clone)]
+pub struct Warning {
+    pub category: Category,
+    pub message: String,
+    pub span: Option<Span>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl ErrorLike for Warning {
+    fn kind() -> &'static str {
+        "Warning"
+    }
+
+    fn code(&self) -> &'static str {
+        self.category.code()
+    }
+
+    fn severity() -> Severity {
+        Severity::Warning
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    fn labels(&self) -> Vec<Label> {
+        self.labels.clone()
+    }
+
+    fn notes(&self) -> Vec<String> {
+        let mut notes = self.notes.clone();
+        notes.push(format!("Enabled by '-W{}'", self.category.name()));
+        notes
+    }
+}
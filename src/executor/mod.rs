@@ -1,4 +1,4 @@
-use crate::compile::CompileError;
+use crate::{assembly::Target, compile::CompileError};
 use std::{
     env::temp_dir,
     fs::{metadata, remove_file, set_permissions},
@@ -8,11 +8,11 @@ use std::{
 };
 
 pub trait Executable {
-    fn execute(&self, filename: &str) -> Result<i32, CompileError>;
+    fn execute(&self, filename: &str, target: Target) -> Result<i32, CompileError>;
 }
 
 impl Executable for String {
-    fn execute(&self, filename: &str) -> Result<i32, CompileError> {
+    fn execute(&self, filename: &str, target: Target) -> Result<i32, CompileError> {
         let object_file = temp_dir().join("program.tmp.o");
         let executable_file = filename.replace(".c", "");
 
@@ -25,10 +25,8 @@ impl Executable for String {
             .arg("-")
             .stdin(Stdio::piped())
             .spawn()
-            .or_else(|_| {
-                Err(CompileError {
-                    message: "Failed to start `clang` process!",
-                })
+            .map_err(|_| CompileError {
+                message: "Failed to start `clang` process!",
             })?;
 
         clang_process
@@ -37,17 +35,13 @@ impl Executable for String {
             .ok_or(CompileError {
                 message: "Failed to open stdin!",
             })?
-            .write(self.as_bytes())
-            .or_else(|_| {
-                Err(CompileError {
-                    message: "Failed to write to stdin!",
-                })
+            .write_all(self.as_bytes())
+            .map_err(|_| CompileError {
+                message: "Failed to write to stdin!",
             })?;
 
-        let status = clang_process.wait().or_else(|_| {
-            Err(CompileError {
-                message: "Failed to wait on `clang` process!",
-            })
+        let status = clang_process.wait().map_err(|_| CompileError {
+            message: "Failed to wait on `clang` process!",
         })?;
 
         if !status.success() {
@@ -56,53 +50,48 @@ impl Executable for String {
             });
         }
 
-        let status = Command::new("ld")
+        // macOS starts the program at `main`, Linux needs the C runtime to call it
+        let mut linker = match target {
+            Target::MacOS => {
+                let mut command = Command::new("ld");
+                command.arg("-e").arg("main").arg("-arch").arg("arm64");
+                command
+            }
+            Target::Linux => Command::new("clang"),
+        };
+        let status = linker
             .arg(object_file.clone())
-            .arg("-e")
-            .arg("main")
-            .arg("-arch")
-            .arg("arm64")
             .arg("-o")
             .arg(executable_file.clone())
             .status()
-            .or_else(|_| {
-                Err(CompileError {
-                    message: "Failed to execute `ld` command!",
-                })
+            .map_err(|_| CompileError {
+                message: "Failed to execute the linker!",
             })?;
 
         if !status.success() {
             return Err(CompileError {
-                message: "`ld` command failed!",
+                message: "Linking failed!",
             });
         }
 
         let mut permissions = metadata(executable_file.clone())
-            .or_else(|_| {
-                Err(CompileError {
-                    message: "Failed to get metadata for output file!",
-                })
+            .map_err(|_| CompileError {
+                message: "Failed to get metadata for output file!",
             })?
             .permissions();
         permissions.set_mode(0o755);
-        set_permissions(executable_file.clone(), permissions).or_else(|_| {
-            Err(CompileError {
-                message: "Failed to set permissions for output file!",
-            })
+        set_permissions(executable_file.clone(), permissions).map_err(|_| CompileError {
+            message: "Failed to set permissions for output file!",
         })?;
 
         let exec_status = Command::new(executable_file.clone())
             .status()
-            .or_else(|_| {
-                Err(CompileError {
-                    message: "Failed to execute the output file!",
-                })
+            .map_err(|_| CompileError {
+                message: "Failed to execute the output file!",
             })?;
 
-        remove_file(object_file).or_else(|_| {
-            Err(CompileError {
-                message: "Failed to remove temporary object file!",
-            })
+        remove_file(object_file).map_err(|_| CompileError {
+            message: "Failed to remove temporary object file!",
         })?;
 
         Ok(exec_status.code().unwrap_or(0))
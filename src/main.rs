@@ -7,46 +7,126 @@ extern crate enum_display_derive;
 
 mod analyzer;
 mod assembly;
+mod checker;
 mod error;
 mod executor;
+mod options;
+mod preprocessor;
+#[cfg(test)]
+mod testing;
 mod tokenizer;
 mod translator;
 mod types;
 
 use analyzer::*;
 use assembly::*;
+use checker::*;
 use colored::Colorize;
+use context::Context;
 use error::*;
 use executor::*;
+use options::Options;
+use preprocessor::Preprocessor;
 use program::Program;
-use std::fs::read_to_string;
+use std::{env::args, fs::read_to_string, process::exit};
 use tokenizer::*;
 use translator::*;
 use types::*;
 
 fn main() {
-    let path = "./assets/sample.c";
-    let file = read_to_string(path).expect(&format!("File at {path} does not exist!"));
+    let options = Options::parse(args().skip(1)).unwrap_or_else(|error| {
+        println!(
+            "{}{} {}",
+            "ArgumentError".red().bold(),
+            ":".red().bold(),
+            error.red()
+        );
+        exit(1)
+    });
+    let path = options.path.as_str();
+    let format = options.format;
+    let file = read_to_string(path).unwrap_or_else(|_| panic!("File at {path} does not exist!"));
 
-    let tokens: Vec<Token> = file.tokenize().collect();
-    println!("{}: {:?}\n", "Tokens".bold().cyan(), tokens);
+    let mut preprocessor = Preprocessor::new(options.includes.clone(), options.defines.clone());
+    let result = preprocessor.include(path, file);
+    let source = preprocessor.finish();
+    result.unwrap_or_else(|error| error.crash(&source, format));
 
-    let ast = tokens.into_iter().analyze();
-    let ast = ast.unwrap_or_else(|error| error.crash(&file, path));
-    println!("{}: {:?}\n", "AST".bold().magenta(), ast);
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut errors = 0;
+    for token in source.tokenize() {
+        match token {
+            Ok(token) => tokens.push(token),
+            Err(error) => {
+                error.emit(&source, format);
+                errors += 1;
+            }
+        }
+    }
+    if errors > 0 {
+        exit(1);
+    }
+    if options.dump {
+        println!("{}: {:?}\n", "Tokens".bold().cyan(), tokens);
+    }
 
-    let mut program = Program::new();
-    ast.translate(&mut program)
-        .unwrap_or_else(|error| error.crash(&file, path));
-    println!("{}:\n{:?}\n", "IR".bold().green(), program);
+    let (ast, errors) = tokens.into_iter().analyze();
+    for error in &errors {
+        error.emit(&source, format);
+    }
+    if !errors.is_empty() {
+        exit(1);
+    }
+    if options.dump {
+        println!("{}: {:?}\n", "AST".bold().magenta(), ast);
+    }
+
+    let mut context = Context::new(options.warnings.clone());
+    let typed = ast
+        .check(&mut context)
+        .unwrap_or_else(|error| error.crash(&source, format));
+    context.check_unused();
+
+    context
+        .warnings
+        .sort_by_key(|warning| warning.span.map_or(usize::MAX, |x| source.position(x)));
+    for warning in &context.warnings {
+        let mut diagnostic = warning.diagnostic();
+        if options.werror {
+            diagnostic.severity = diagnostic::Severity::Error;
+        }
+        diagnostic.emit(&source, format);
+    }
+    context
+        .errors
+        .sort_by_key(|error| error.span.map_or(usize::MAX, |x| source.position(x)));
+    for error in &context.errors {
+        error.emit(&source, format);
+    }
+    if !context.errors.is_empty() || (options.werror && !context.warnings.is_empty()) {
+        exit(1);
+    }
+
+    let mut program = Program::new(
+        context.globals,
+        context.locals,
+        context.storage,
+        context.qualifiers,
+    );
+    typed.translate(&mut program);
+    if options.dump {
+        println!("{}:\n{:?}\n", "IR".bold().green(), program);
+    }
 
     let assembly = program
-        .assemble()
-        .unwrap_or_else(|error| error.crash(&file, path));
-    println!("{}:\n{}\n", "ASM".bold().yellow(), assembly);
+        .assemble(options.target)
+        .unwrap_or_else(|error| error.crash(&source, format));
+    if options.dump {
+        println!("{}:\n{}\n", "ASM".bold().yellow(), assembly);
+    }
 
     let result = assembly
-        .execute(path)
-        .unwrap_or_else(|error| error.crash(&file, path));
+        .execute(path, options.target)
+        .unwrap_or_else(|error| error.crash(&source, format));
     println!("{}: {}\n", "Execution Result".bold().blue(), result);
 }
@@ -0,0 +1,169 @@
+use crate::{assembly::Target, diagnostic::Format, warning::Category};
+use std::collections::HashSet;
+
+/// Command line arguments: `[-I path] [-D name[=value]] [-U name] [-W[no-]name] [-Wall]
+/// [-Werror] [--error-format=pretty|json] [--target=macos|linux] [--dump] [file]`
+pub struct Options {
+    pub path: String,
+    pub includes: Vec<String>,
+    /// Macros from `-D` and `-U` in the order they were given, the undefined ones have no value
+    pub defines: Vec<(String, Option<String>)>,
+    pub format: Format,
+    /// Warnings that are reported, `-Wall` enables all of them
+    pub warnings: HashSet<Category>,
+    /// Whether warnings fail the compilation like errors, which is set by `-Werror`
+    pub werror: bool,
+    /// Whether the tokens, the AST, the IR and the assembly are printed, which is set by `--dump`
+    pub dump: bool,
+    /// Object format of the assembly, which is set by `--target`
+    pub target: Target,
+}
+
+impl Options {
+    pub fn parse(mut arguments: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            path: "./assets/sample.c".to_owned(),
+            includes: Vec::new(),
+            defines: Vec::new(),
+            format: Format::Pretty,
+            warnings: Category::ALL
+                .into_iter()
+                .filter(|x| x.enabled_by_default())
+                .collect(),
+            werror: false,
+            dump: false,
+            target: Target::MacOS,
+        };
+
+        while let Some(argument) = arguments.next() {
+            // Values of the flags are either attached to them or passed separately
+            let mut value = |flag: &str| match &argument[flag.len()..] {
+                "" => arguments
+                    .next()
+                    .ok_or(format!("Missing value after '{}'!", flag)),
+                value => Ok(value.to_owned()),
+            };
+
+            match argument.as_str() {
+                x if x.starts_with("-I") => options.includes.push(value("-I")?),
+                x if x.starts_with("-D") => {
+                    let define = value("-D")?;
+                    let (name, value) = define.split_once('=').unwrap_or((&define, "1"));
+                    options
+                        .defines
+                        .push((name.to_owned(), Some(value.to_owned())));
+                }
+                x if x.starts_with("-U") => options.defines.push((value("-U")?, None)),
+                "-Wall" => options.warnings.extend(Category::ALL),
+                "-Werror" => options.werror = true,
+                "--dump" => options.dump = true,
+                x if x.starts_with("-W") => {
+                    let (name, enabled) = match x.strip_prefix("-Wno-") {
+                        Some(name) => (name, false),
+                        None => (&x[2..], true),
+                    };
+                    let Some(category) = Category::parse(name) else {
+                        return Err(format!("Unknown warning '{}'!", x));
+                    };
+                    match enabled {
+                        true => options.warnings.insert(category),
+                        false => options.warnings.remove(&category),
+                    };
+                }
+                x if x.starts_with("--error-format") => {
+                    options.format = match value("--error-format")?.trim_start_matches('=') {
+                        "pretty" => Format::Pretty,
+                        "json" => Format::Json,
+                        x => return Err(format!("Unknown error format '{}'!", x)),
+                    }
+                }
+                x if x.starts_with("--target") => {
+                    options.target = match value("--target")?.trim_start_matches('=') {
+                        "macos" => Target::MacOS,
+                        "linux" => Target::Linux,
+                        x => return Err(format!("Unknown target '{}'!", x)),
+                    }
+                }
+                x if x.starts_with('-') => return Err(format!("Unknown option '{}'!", x)),
+                _ => options.path = argument.clone(),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(arguments: &str) -> Result<Options, String> {
+        Options::parse(arguments.split_whitespace().map(str::to_owned))
+    }
+
+    #[test]
+    fn warnings() {
+        let options = parse("").unwrap();
+        assert!(options.warnings.contains(&Category::UnusedVariable));
+        assert!(!options.warnings.contains(&Category::Shadow));
+        assert!(!options.werror);
+
+        let options = parse("-Wshadow -Wno-unused-variable -Werror").unwrap();
+        assert!(options.warnings.contains(&Category::Shadow));
+        assert!(!options.warnings.contains(&Category::UnusedVariable));
+        assert!(options.werror);
+
+        // Later flags override the earlier ones
+        let options = parse("-Wall -Wno-float-conversion").unwrap();
+        let expected = Category::ALL.len() - 1;
+        assert_eq!(options.warnings.len(), expected);
+        assert!(!options.warnings.contains(&Category::FloatConversion));
+        let options = parse("-Wno-shadow -Wall").unwrap();
+        assert_eq!(options.warnings.len(), Category::ALL.len());
+
+        assert_eq!(
+            parse("-Wfoo").err(),
+            Some("Unknown warning '-Wfoo'!".to_owned())
+        );
+        assert_eq!(
+            parse("-Wno-foo").err(),
+            Some("Unknown warning '-Wno-foo'!".to_owned())
+        );
+    }
+
+    #[test]
+    fn values() {
+        let options =
+            parse("-I a -Ib -DX -DY=2 -U X --error-format=json --target linux main.c").unwrap();
+        assert_eq!(options.includes, ["a", "b"]);
+        let defines = [
+            ("X".to_owned(), Some("1".to_owned())),
+            ("Y".to_owned(), Some("2".to_owned())),
+            ("X".to_owned(), None),
+        ];
+        assert_eq!(options.defines, defines);
+        assert_eq!(options.format, Format::Json);
+        assert_eq!(options.target, Target::Linux);
+        assert_eq!(options.path, "main.c");
+        assert!(!options.dump);
+
+        let options = parse("--error-format pretty --target=macos --dump").unwrap();
+        assert_eq!(options.format, Format::Pretty);
+        assert_eq!(options.target, Target::MacOS);
+        assert!(options.dump);
+
+        let errors = [
+            ("-I", "Missing value after '-I'!"),
+            ("--error-format=xml", "Unknown error format 'xml'!"),
+            ("--target=windows", "Unknown target 'windows'!"),
+            ("--verbose", "Unknown option '--verbose'!"),
+        ];
+        for (arguments, error) in errors {
+            assert_eq!(
+                parse(arguments).err(),
+                Some(error.to_owned()),
+                "{arguments}"
+            );
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{
+    ast::{BinaryOperator, Data, Expression, UnaryOperator, Value},
+    Span, Token, Tokenizable,
+};
+use std::iter::once;
+
+/// Value of an `#if` expression, whose macros and `defined` operators were already replaced
+pub fn evaluate(text: &str) -> Option<i64> {
+    let tokens = text.tokenize().collect::<Result<Vec<_>, _>>().ok()?;
+    let mut stream = tokens
+        .into_iter()
+        .chain(once(Token::Symbol(";", Span::default())))
+        .peekable();
+    let (expression, _) = Expression::from_stream(&mut stream, vec![";"]).ok()?;
+    match stream.next() {
+        Some(_) => None,
+        None => fold(&expression),
+    }
+}
+
+/// Computes the expression with the wrapping arithmetic of `long`
+fn fold(expression: &Expression) -> Option<i64> {
+    Some(match expression {
+        Expression::Value(Value::Data(Data::Float(_)), _) => return None,
+        Expression::Value(Value::Data(data), _) => i64::from(data),
+        Expression::Unary { op, lhs, .. } => {
+            let value = fold(lhs)?;
+            match op {
+                UnaryOperator::Negation => value.wrapping_neg(),
+                UnaryOperator::Identity => value,
+                UnaryOperator::Inversion => (value == 0) as i64,
+                UnaryOperator::Complement => !value,
+            }
+        }
+        Expression::Binary { op, lhs, rhs, .. } => {
+            let lhs = fold(lhs)?;
+            // The right side of logical operators is only evaluated when it matters
+            match op {
+                BinaryOperator::And if lhs == 0 => return Some(0),
+                BinaryOperator::Or if lhs != 0 => return Some(1),
+                _ => (),
+            }
+
+            let rhs = fold(rhs)?;
+            match op {
+                BinaryOperator::Addition => lhs.wrapping_add(rhs),
+                BinaryOperator::Subtraction => lhs.wrapping_sub(rhs),
+                BinaryOperator::Multiplication => lhs.wrapping_mul(rhs),
+                BinaryOperator::Division => lhs.checked_div(rhs)?,
+                BinaryOperator::Remainder => lhs.checked_rem(rhs)?,
+                BinaryOperator::Greater => (lhs > rhs) as i64,
+                BinaryOperator::Less => (lhs < rhs) as i64,
+                BinaryOperator::GreaterEqual => (lhs >= rhs) as i64,
+                BinaryOperator::LessEqual => (lhs <= rhs) as i64,
+                BinaryOperator::Equal => (lhs == rhs) as i64,
+                BinaryOperator::NotEqual => (lhs != rhs) as i64,
+                BinaryOperator::And | BinaryOperator::Or => (rhs != 0) as i64,
+                BinaryOperator::BitwiseAnd => lhs & rhs,
+                BinaryOperator::BitwiseOr => lhs | rhs,
+                BinaryOperator::BitwiseXor => lhs ^ rhs,
+                BinaryOperator::LeftShift => lhs.wrapping_shl(rhs as u32),
+                BinaryOperator::RightShift => lhs.wrapping_shr(rhs as u32),
+            }
+        }
+        Expression::Conditional {
+            condition,
+            then,
+            otherwise,
+            ..
+        } => match fold(condition)? {
+            0 => fold(otherwise)?,
+            _ => fold(then)?,
+        },
+        Expression::Comma { lhs, rhs, .. } => {
+            fold(lhs)?;
+            fold(rhs)?
+        }
+        _ => return None,
+    })
+}
@@ -0,0 +1,337 @@
+use crate::tokenizer::length;
+use std::collections::{HashMap, HashSet};
+
+pub struct Macro {
+    /// Parameters of a function-like macro, variadic arguments are gathered into `__VA_ARGS__`
+    pub parameters: Option<Vec<String>>,
+    pub variadic: bool,
+    pub body: Vec<String>,
+}
+
+/// Splits the text into preprocessing tokens along with their offsets, whitespace included
+/// so that the text can be copied over as is. Whatever the tokenizer rejects is taken
+/// a character at a time and left for it to report
+pub fn lex(text: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    while let Some(first) = text[position..].chars().next() {
+        let rest = &text[position..];
+        let whitespace = rest.len() - rest.trim_start().len();
+        let length = match rest {
+            _ if whitespace > 0 => whitespace,
+            _ if rest.starts_with("##") => 2,
+            _ if rest.starts_with("...") => 3,
+            _ => length(rest).unwrap_or(first.len_utf8()),
+        };
+        tokens.push((position, &rest[..length]));
+        position += length;
+    }
+    tokens
+}
+
+/// Whitespace is collapsed into a single space, since only its presence matters
+pub fn normalize(token: &str) -> String {
+    match token.trim().is_empty() {
+        true => " ".to_owned(),
+        false => token.to_owned(),
+    }
+}
+
+pub fn is_identifier(token: &str) -> bool {
+    token.starts_with(|x: char| x.is_alphabetic() || x == '_')
+}
+
+/// Drops the whitespace around the tokens
+pub fn trim(tokens: &[String]) -> &[String] {
+    let start = tokens.iter().position(|x| x != " ").unwrap_or(tokens.len());
+    let end = tokens
+        .iter()
+        .rposition(|x| x != " ")
+        .map_or(start, |x| x + 1);
+    &tokens[start..end]
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Expands macros where `__FILE__` and `__LINE__` refer to the location
+pub struct Expander<'a> {
+    pub macros: &'a HashMap<String, Macro>,
+    pub file: &'a str,
+    pub line: usize,
+}
+
+impl<'a> Expander<'a> {
+    /// Expands every macro in the tokens, except for the hidden ones that are being expanded already
+    pub fn expand(
+        &self,
+        tokens: &[String],
+        hidden: &HashSet<String>,
+    ) -> Result<Vec<String>, String> {
+        Ok(self.rescan(tokens, tokens.len(), hidden)?.0)
+    }
+
+    /// Expands the macros invoked within the first `length` tokens, whose arguments may take
+    /// the tokens after them, along with how many of those were taken
+    fn rescan(
+        &self,
+        tokens: &[String],
+        length: usize,
+        hidden: &HashSet<String>,
+    ) -> Result<(Vec<String>, usize), String> {
+        let mut result = Vec::new();
+        let mut index = 0;
+        while index < length {
+            match self.invoke(&tokens[index..], hidden)? {
+                // Spaces keep the expansion from merging with the tokens around it
+                Some((expansion, consumed)) => {
+                    result.push(" ".to_owned());
+                    result.extend(expansion);
+                    result.push(" ".to_owned());
+                    index += consumed;
+                }
+                None => {
+                    result.push(tokens[index].clone());
+                    index += 1;
+                }
+            }
+        }
+
+        Ok((result, index - length))
+    }
+
+    /// Expands the macro invoked at the start of the tokens,
+    /// along with how many of the tokens the invocation took
+    pub fn invoke(
+        &self,
+        tokens: &[String],
+        hidden: &HashSet<String>,
+    ) -> Result<Option<(Vec<String>, usize)>, String> {
+        let name = tokens[0].as_str();
+        match name {
+            "__FILE__" => return Ok(Some((vec![format!("\"{}\"", escape(self.file))], 1))),
+            "__LINE__" => return Ok(Some((vec![self.line.to_string()], 1))),
+            _ => (),
+        }
+
+        let Some(definition) = self.macros.get(name) else {
+            return Ok(None);
+        };
+        if hidden.contains(name) {
+            return Ok(None);
+        }
+
+        let (arguments, consumed) = match &definition.parameters {
+            None => (Vec::new(), 1),
+            Some(parameters) => match arguments(name, &tokens[1..])? {
+                // A function-like macro without arguments is just a name
+                None => return Ok(None),
+                Some((arguments, consumed)) => {
+                    (bind(name, definition, parameters, arguments)?, consumed + 1)
+                }
+            },
+        };
+
+        let parameter = |token: &str| match &definition.parameters {
+            Some(parameters) => parameters.iter().position(|x| x == token),
+            None => None,
+        };
+        let next = |index: usize| {
+            definition.body[index..]
+                .iter()
+                .position(|x| x != " ")
+                .map(|x| x + index)
+        };
+
+        let mut body: Vec<String> = Vec::new();
+        let mut index = 0;
+        while index < definition.body.len() {
+            let token = &definition.body[index];
+            index += 1;
+
+            // `#parameter` turns the argument into a string literal
+            if token == "#"
+                && definition.parameters.is_some()
+                && let Some(next) = next(index)
+                && let Some(parameter) = parameter(&definition.body[next])
+            {
+                body.push(stringify(&arguments[parameter]));
+                index = next + 1;
+                continue;
+            }
+
+            let Some(parameter) = parameter(token) else {
+                body.push(token.clone());
+                continue;
+            };
+
+            // Operands of `##` are pasted as written instead of being expanded first
+            let pasted = body
+                .iter()
+                .rev()
+                .find(|x| *x != " ")
+                .is_some_and(|x| x == "##")
+                || next(index).is_some_and(|x| definition.body[x] == "##");
+            if pasted {
+                body.extend(arguments[parameter].iter().cloned());
+            } else {
+                body.extend(self.expand(&arguments[parameter], hidden)?);
+            }
+        }
+
+        let mut hidden = hidden.clone();
+        hidden.insert(name.to_owned());
+
+        // The expansion is rescanned along with the rest of the tokens, so a function-like
+        // macro at its end takes its arguments from them. They can only do so if they
+        // start with a parenthesis, which saves copying them otherwise
+        let mut body = paste(body);
+        let length = body.len();
+        let rest = &tokens[consumed..];
+        if rest.iter().find(|x| *x != " ").is_some_and(|x| x == "(") {
+            body.extend(rest.iter().cloned());
+        }
+        let (expansion, taken) = self.rescan(&body, length, &hidden)?;
+        Ok(Some((expansion, consumed + taken)))
+    }
+}
+
+/// Tokens of each argument of a macro invocation
+type Arguments = Vec<Vec<String>>;
+
+/// Splits the parenthesized arguments that follow a macro name,
+/// along with how many of the tokens they took
+fn arguments(name: &str, tokens: &[String]) -> Result<Option<(Arguments, usize)>, String> {
+    let Some(open) = tokens.iter().position(|x| x != " ") else {
+        return Ok(None);
+    };
+    if tokens[open] != "(" {
+        return Ok(None);
+    }
+
+    let mut arguments = vec![Vec::new()];
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(open + 1) {
+        match token.as_str() {
+            ")" if depth == 0 => {
+                let arguments = arguments.iter().map(|x| trim(x).to_vec()).collect();
+                return Ok(Some((arguments, index + 1)));
+            }
+            "," if depth == 0 => {
+                arguments.push(Vec::new());
+                continue;
+            }
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            _ => (),
+        }
+        arguments.last_mut().unwrap().push(token.clone());
+    }
+
+    Err(format!("Unterminated invocation of macro '{}'!", name))
+}
+
+/// Matches the arguments with the parameters, gathering the variadic ones together
+fn bind(
+    name: &str,
+    definition: &Macro,
+    parameters: &[String],
+    mut arguments: Vec<Vec<String>>,
+) -> Result<Vec<Vec<String>>, String> {
+    // `MACRO()` passes a single empty argument, which is no arguments at all
+    if parameters.is_empty() && arguments.len() == 1 && arguments[0].is_empty() {
+        arguments.clear();
+    }
+
+    if definition.variadic && arguments.len() >= parameters.len() {
+        let rest = arguments.split_off(parameters.len() - 1);
+        arguments.push(rest.join(&",".to_owned()));
+    } else if definition.variadic && arguments.len() + 1 == parameters.len() {
+        arguments.push(Vec::new());
+    }
+
+    if arguments.len() != parameters.len() {
+        return Err(format!(
+            "Macro '{}' takes {} arguments, but was given {}!",
+            name,
+            parameters.len(),
+            arguments.len()
+        ));
+    }
+    Ok(arguments)
+}
+
+fn stringify(tokens: &[String]) -> String {
+    let text: String = tokens
+        .iter()
+        .map(|x| match x.starts_with(['"', '\'']) {
+            true => escape(x),
+            false => x.clone(),
+        })
+        .collect();
+
+    format!("\"{}\"", text)
+}
+
+/// Joins the operands around every `##` into a single token
+fn paste(tokens: Vec<String>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        if token != "##" {
+            result.push(token);
+            continue;
+        }
+
+        while result.last().is_some_and(|x| x == " ") {
+            result.pop();
+        }
+        let right = tokens.by_ref().find(|x| x != " ").unwrap_or_default();
+        match result.last_mut() {
+            Some(left) => *left += &right,
+            None => result.push(right),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        let tokens: Vec<_> = lex("a##b(...)  \"s\\\"\" 1.5e+3f x->y<<=2 'c #\"")
+            .into_iter()
+            .map(|x| x.1)
+            .collect();
+        let expected = [
+            "a",
+            "##",
+            "b",
+            "(",
+            "...",
+            ")",
+            "  ",
+            "\"s\\\"\"",
+            " ",
+            "1.5e+3f",
+            " ",
+            "x",
+            "->",
+            "y",
+            "<<=",
+            "2",
+            " ",
+            "'",
+            "c",
+            " ",
+            "#",
+            "\"",
+        ];
+        assert_eq!(tokens, expected);
+        assert_eq!(lex("  x")[1], (2, "x"));
+    }
+}
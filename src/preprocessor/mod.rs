@@ -0,0 +1,783 @@
+mod condition;
+mod macros;
+mod source;
+
+pub use source::Source;
+
+use crate::{preprocess::PreprocessError, Span};
+use macros::{is_identifier, lex, normalize, trim, Expander, Macro};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// Includes nested deeper than this are assumed to be including themselves forever
+const MAX_DEPTH: usize = 200;
+
+pub struct Preprocessor {
+    includes: Vec<String>,
+    macros: HashMap<String, Macro>,
+    /// Files marked with `#pragma once`
+    once: HashSet<PathBuf>,
+    depth: usize,
+    source: Source,
+}
+
+/// File being preprocessed
+struct Input {
+    index: usize,
+    text: Rc<str>,
+    /// The text with comments and line splices blanked out
+    clean: String,
+    /// Offsets at which the lines start
+    lines: Vec<usize>,
+}
+
+impl Input {
+    /// Span of a slice of the file's text
+    fn span(&self, slice: &str) -> Span {
+        let start = slice.as_ptr() as usize - self.text.as_ptr() as usize;
+        Span {
+            file: self.index,
+            start,
+            end: start + slice.len(),
+        }
+    }
+}
+
+/// State of an `#if` group
+struct Condition<'a> {
+    /// Whether the group is within a compiled one
+    parent: bool,
+    active: bool,
+    taken: bool,
+    otherwise: bool,
+    token: &'a str,
+}
+
+impl Preprocessor {
+    /// Creates a preprocessor with include paths and macros to define,
+    /// the ones without a value are undefined instead
+    pub fn new(includes: Vec<String>, defines: Vec<(String, Option<String>)>) -> Self {
+        let mut macros = HashMap::new();
+        for (name, value) in defines {
+            match value {
+                Some(value) => {
+                    let body = lex(&value).iter().map(|x| normalize(x.1)).collect();
+                    let definition = Macro {
+                        parameters: None,
+                        variadic: false,
+                        body,
+                    };
+                    macros.insert(name, definition);
+                }
+                None => {
+                    macros.remove(&name);
+                }
+            }
+        }
+
+        Preprocessor {
+            includes,
+            macros,
+            once: HashSet::new(),
+            depth: 0,
+            source: Source::new(),
+        }
+    }
+
+    pub fn finish(self) -> Source {
+        self.source
+    }
+
+    /// Preprocesses the text of a file, appending the result to the source
+    pub fn include(&mut self, path: &str, text: String) -> Result<(), PreprocessError> {
+        let (index, text) = self.source.load(path, text);
+        let clean = clean(&text);
+        let lines = [0]
+            .into_iter()
+            .chain(text.match_indices('\n').map(|x| x.0 + 1))
+            .collect();
+        let input = Input {
+            index,
+            text,
+            clean,
+            lines,
+        };
+
+        let mut conditions: Vec<Condition> = Vec::new();
+        let mut run: Option<usize> = None;
+        let mut offset = 0;
+        for line in input.clean.split_inclusive('\n') {
+            let start = offset;
+            offset += line.len();
+
+            let active = conditions.last().is_none_or(|x| x.active);
+            let directive = line.trim_start().strip_prefix('#');
+            if directive.is_none() && active {
+                run = run.or(Some(start));
+                continue;
+            }
+
+            if let Some(run) = run.take() {
+                self.text(&input, run, start)?;
+            }
+            if let Some(directive) = directive {
+                let start = offset - directive.len();
+                self.directive(&input, start, offset, &mut conditions)?;
+                if line.ends_with('\n') {
+                    self.source.verbatim("\n", index, offset - 1);
+                }
+            }
+        }
+
+        if let Some(run) = run {
+            self.text(&input, run, offset)?;
+        }
+        match conditions.pop() {
+            Some(condition) => Err(PreprocessError {
+                code: "E0101",
+                message: format!("Unterminated '#{}'!", condition.token),
+                span: Some(input.span(condition.token)),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn expander(&self, input: &Input, offset: usize) -> Expander<'_> {
+        Expander {
+            macros: &self.macros,
+            file: self.source.path(input.index),
+            line: input.lines.partition_point(|&x| x <= offset),
+        }
+    }
+
+    /// Copies the text over while expanding the macros in it
+    fn text(&mut self, input: &Input, start: usize, end: usize) -> Result<(), PreprocessError> {
+        let tokens = lex(&input.clean[start..end]);
+        let normalized: Vec<String> = tokens.iter().map(|x| normalize(x.1)).collect();
+
+        let mut index = 0;
+        while index < tokens.len() {
+            let (at, token) = tokens[index];
+            let offset = start + at;
+            let expansion = match is_identifier(token) {
+                true => self
+                    .expander(input, offset)
+                    .invoke(&normalized[index..], &HashSet::new())
+                    .map_err(|message| PreprocessError {
+                        code: "E0102",
+                        message,
+                        span: Some(input.span(&input.text[offset..offset + token.len()])),
+                    })?,
+                false => None,
+            };
+
+            match expansion {
+                Some((expansion, consumed)) => {
+                    let (last, text) = tokens[index + consumed - 1];
+                    let length = last + text.len() - at;
+                    let text = format!(" {} ", expansion.concat());
+                    self.source.expanded(&text, input.index, offset, length);
+                    index += consumed;
+                }
+                None => {
+                    self.source.verbatim(token, input.index, offset);
+                    index += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles the directive that starts right after the `#` and ends with the line
+    fn directive<'a>(
+        &mut self,
+        input: &'a Input,
+        start: usize,
+        end: usize,
+        conditions: &mut Vec<Condition<'a>>,
+    ) -> Result<(), PreprocessError> {
+        let tokens: Vec<_> = lex(&input.clean[start..end])
+            .into_iter()
+            .filter(|x| !x.1.trim().is_empty())
+            .map(|(at, token)| &input.text[start + at..start + at + token.len()])
+            .collect();
+        let Some(&name) = tokens.first() else {
+            return Ok(());
+        };
+        // Everything after the directive name
+        let rest = input.span(name).end;
+        let rest = (rest, &input.clean[rest..end]);
+
+        let error = |code, message: String, token: &str| PreprocessError {
+            code,
+            message,
+            span: Some(input.span(token)),
+        };
+        let unmatched = || error("E0103", format!("'#{}' without '#if'!", name), name);
+
+        let active = conditions.last().is_none_or(|x| x.active);
+        match name {
+            "if" | "ifdef" | "ifndef" => {
+                let value = active
+                    && match name {
+                        "if" => self.condition(input, rest, name)?,
+                        _ => {
+                            let defined = self.defined(input, tokens.get(1).copied(), name)?;
+                            defined == (name == "ifdef")
+                        }
+                    };
+
+                conditions.push(Condition {
+                    parent: active,
+                    active: value,
+                    taken: value,
+                    otherwise: false,
+                    token: name,
+                });
+            }
+            "elif" => {
+                let condition = conditions.last_mut().ok_or_else(unmatched)?;
+                if condition.otherwise {
+                    return Err(error("E0104", "'#elif' after '#else'!".to_owned(), name));
+                }
+
+                let value =
+                    condition.parent && !condition.taken && self.condition(input, rest, name)?;
+                condition.active = value;
+                condition.taken |= value;
+            }
+            "else" => {
+                let condition = conditions.last_mut().ok_or_else(unmatched)?;
+                if condition.otherwise {
+                    return Err(error("E0104", "'#else' after '#else'!".to_owned(), name));
+                }
+
+                condition.active = condition.parent && !condition.taken;
+                condition.taken = true;
+                condition.otherwise = true;
+            }
+            "endif" => {
+                conditions.pop().ok_or_else(unmatched)?;
+            }
+            _ if !active => (),
+            "include" => self.import(input, rest, name)?,
+            "define" => self.define(input, rest, name)?,
+            "undef" => {
+                let name = self.name(input, tokens.get(1).copied(), name)?;
+                self.macros.remove(name);
+            }
+            "error" => return Err(error("E0105", format!("#error {}", rest.1.trim()), name)),
+            "pragma" if tokens.get(1) == Some(&"once") => {
+                let path = Path::new(self.source.path(input.index));
+                self.once
+                    .insert(path.canonicalize().unwrap_or(path.to_owned()));
+            }
+            "pragma" | "line" => (),
+            _ => {
+                return Err(error(
+                    "E0106",
+                    format!("Unknown preprocessor directive '#{}'!", name),
+                    name,
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name<'a>(
+        &self,
+        input: &Input,
+        token: Option<&'a str>,
+        directive: &'a str,
+    ) -> Result<&'a str, PreprocessError> {
+        match token {
+            Some(token) if is_identifier(token) => Ok(token),
+            token => Err(PreprocessError {
+                code: "E0107",
+                message: format!("Expected a macro name after '{}'!", directive),
+                span: Some(input.span(token.unwrap_or(directive))),
+            }),
+        }
+    }
+
+    fn defined(
+        &self,
+        input: &Input,
+        token: Option<&str>,
+        directive: &str,
+    ) -> Result<bool, PreprocessError> {
+        let name = self.name(input, token, directive)?;
+        Ok(self.macros.contains_key(name) || ["__FILE__", "__LINE__"].contains(&name))
+    }
+
+    fn condition(
+        &self,
+        input: &Input,
+        (start, text): (usize, &str),
+        directive: &str,
+    ) -> Result<bool, PreprocessError> {
+        let tokens: Vec<_> = lex(text)
+            .into_iter()
+            .map(|(at, token)| &input.text[start + at..start + at + token.len()])
+            .collect();
+
+        // `defined` is resolved before the expansion, so that the names it checks stay intact
+        let mut resolved = Vec::new();
+        let mut significant = tokens.into_iter().filter(|x| !x.trim().is_empty());
+        while let Some(token) = significant.next() {
+            if token != "defined" {
+                resolved.extend([token.to_owned(), " ".to_owned()]);
+                continue;
+            }
+
+            let defined = match significant.next() {
+                Some("(") => {
+                    let defined = self.defined(input, significant.next(), token)?;
+                    match significant.next() {
+                        Some(")") => defined,
+                        _ => {
+                            return Err(PreprocessError {
+                                code: "E0108",
+                                message: "Expected ')' after the name in 'defined'!".to_owned(),
+                                span: Some(input.span(token)),
+                            })
+                        }
+                    }
+                }
+                name => self.defined(input, name, token)?,
+            };
+            resolved.push((defined as i32).to_string());
+        }
+
+        let expanded = self
+            .expander(input, start)
+            .expand(&resolved, &HashSet::new())
+            .map_err(|message| PreprocessError {
+                code: "E0102",
+                message,
+                span: Some(input.span(directive)),
+            })?;
+
+        // Names that are left after the expansion are not macros and count as zero
+        let text = expanded
+            .iter()
+            .map(|x| if is_identifier(x) { "0" } else { x })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        match condition::evaluate(&text) {
+            Some(value) => Ok(value != 0),
+            None => Err(PreprocessError {
+                code: "E0109",
+                message: format!("Invalid constant expression in '#{}'!", directive),
+                span: Some(input.span(directive)),
+            }),
+        }
+    }
+
+    fn define(
+        &mut self,
+        input: &Input,
+        (start, text): (usize, &str),
+        directive: &str,
+    ) -> Result<(), PreprocessError> {
+        let tokens = lex(text);
+        let first = tokens.iter().position(|x| !x.1.trim().is_empty());
+        let token = first.map(|x| &input.text[start + tokens[x].0..][..tokens[x].1.len()]);
+        let name = self.name(input, token, directive)?;
+        let mut rest = tokens[first.unwrap() + 1..].iter().map(|x| x.1);
+
+        // Only a parenthesis right after the name makes a function-like macro
+        let mut parameters = None;
+        let mut variadic = false;
+        if tokens.get(first.unwrap() + 1).is_some_and(|x| x.1 == "(") {
+            rest.next();
+            let invalid = || PreprocessError {
+                code: "E0110",
+                message: format!("Invalid parameter list of macro '{}'!", name),
+                span: Some(input.span(name)),
+            };
+
+            let mut names = Vec::new();
+            let mut significant = rest.by_ref().filter(|x| !x.trim().is_empty());
+            loop {
+                match significant.next() {
+                    Some(")") if names.is_empty() => break,
+                    Some("...") => {
+                        names.push("__VA_ARGS__".to_owned());
+                        variadic = true;
+                    }
+                    Some(name) if is_identifier(name) => names.push(name.to_owned()),
+                    _ => return Err(invalid()),
+                }
+                match significant.next() {
+                    Some(")") => break,
+                    Some(",") if !variadic => (),
+                    _ => return Err(invalid()),
+                }
+            }
+            parameters = Some(names);
+        }
+
+        let body: Vec<_> = rest.map(normalize).collect();
+        let definition = Macro {
+            parameters,
+            variadic,
+            body: trim(&body).to_vec(),
+        };
+        self.macros.insert(name.to_owned(), definition);
+        Ok(())
+    }
+
+    /// Handles `#include "file"`, which is looked up next to the current file first,
+    /// and `#include <file>`, which is only looked up in the include paths
+    fn import(
+        &mut self,
+        input: &Input,
+        (start, text): (usize, &str),
+        directive: &str,
+    ) -> Result<(), PreprocessError> {
+        let offset = start + text.len() - text.trim_start().len();
+        let token = &input.text[offset..start + text.trim_end().len()];
+        let token = if token.is_empty() { directive } else { token };
+
+        // The file name can come from a macro as well
+        let text = match text.trim() {
+            x if x.starts_with(['"', '<']) => x.to_owned(),
+            x => {
+                let tokens: Vec<_> = lex(x).iter().map(|x| normalize(x.1)).collect();
+                let expanded = self
+                    .expander(input, offset)
+                    .expand(&tokens, &HashSet::new())
+                    .map_err(|message| PreprocessError {
+                        code: "E0102",
+                        message,
+                        span: Some(input.span(token)),
+                    })?;
+                trim(&expanded).concat()
+            }
+        };
+
+        let name = match text.chars().next() {
+            Some('"') => text[1..].split_once('"').map(|x| (x.0, true)),
+            Some('<') => text[1..].split_once('>').map(|x| (x.0, false)),
+            _ => None,
+        };
+        let Some((name, quoted)) = name else {
+            return Err(PreprocessError {
+                code: "E0111",
+                message: "Expected \"file\" or <file> after '#include'!".to_owned(),
+                span: Some(input.span(token)),
+            });
+        };
+
+        let directory = Path::new(self.source.path(input.index))
+            .parent()
+            .map(|x| x.to_owned());
+        let path = directory
+            .filter(|_| quoted)
+            .into_iter()
+            .chain(self.includes.iter().map(PathBuf::from))
+            .map(|x| x.join(name))
+            .find(|x| x.is_file());
+
+        let error = |code, message: String| PreprocessError {
+            code,
+            message,
+            span: Some(input.span(token)),
+        };
+        let Some(path) = path else {
+            return Err(error(
+                "E0112",
+                format!("Cannot find include file '{}'!", name),
+            ));
+        };
+        if self.depth >= MAX_DEPTH {
+            return Err(error("E0113", "Includes are nested too deeply!".to_owned()));
+        }
+        if self
+            .once
+            .contains(&path.canonicalize().unwrap_or(path.clone()))
+        {
+            return Ok(());
+        }
+
+        let text = read_to_string(&path)
+            .map_err(|_| error("E0114", format!("Cannot read include file '{}'!", name)))?;
+        self.depth += 1;
+        self.include(&path.to_string_lossy(), text)?;
+        self.depth -= 1;
+
+        // Keeps the last line of the included file apart from what follows
+        self.source.expanded("\n", input.index, offset, token.len());
+        Ok(())
+    }
+}
+
+/// Blanks out comments and line splices without moving anything, so that offsets stay valid
+fn clean(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut result = bytes.to_vec();
+    let mut quote = None;
+    let mut index = 0;
+    while index < bytes.len() {
+        let blank = |result: &mut Vec<u8>, start: usize, end: usize| {
+            for byte in &mut result[start..end] {
+                if *byte != b'\n' {
+                    *byte = b' ';
+                }
+            }
+        };
+
+        match (quote, bytes[index], bytes.get(index + 1)) {
+            (_, b'\\', Some(b'\n')) => {
+                result[index] = b' ';
+                result[index + 1] = b' ';
+                index += 1;
+            }
+            (Some(_), b'\\', Some(_)) => index += 1,
+            (Some(x), y, _) if x == y || y == b'\n' => quote = None,
+            (Some(_), ..) => (),
+            (None, x @ (b'"' | b'\''), _) => quote = Some(x),
+            (None, b'/', Some(b'/')) => {
+                let end = text[index..].find('\n').map_or(text.len(), |x| index + x);
+                blank(&mut result, index, end);
+                index = end;
+                continue;
+            }
+            (None, b'/', Some(b'*')) => match text[index + 2..].find("*/") {
+                Some(end) => {
+                    let end = index + 2 + end + 2;
+                    blank(&mut result, index, end);
+                    index = end;
+                    continue;
+                }
+                // Left for the tokenizer to deal with
+                None => break,
+            },
+            _ => (),
+        }
+        index += 1;
+    }
+
+    String::from_utf8(result).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorLike;
+    use std::{env::temp_dir, fs, process};
+
+    /// Preprocesses the text with the options, the result is given with its whitespace collapsed
+    fn run(
+        path: &str,
+        text: &str,
+        includes: &[&Path],
+        defines: Vec<(String, Option<String>)>,
+    ) -> (Result<String, PreprocessError>, Source) {
+        let includes = includes.iter().map(|x| x.display().to_string()).collect();
+        let mut preprocessor = Preprocessor::new(includes, defines);
+        let result = preprocessor.include(path, text.to_owned());
+        let source = preprocessor.finish();
+        let text = source.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        (result.map(|_| text), source)
+    }
+
+    fn preprocess(text: &str) -> String {
+        let (result, _) = run("main.c", text, &[], Vec::new());
+        result.unwrap_or_else(|error| panic!("{} in {text:?}", error.message()))
+    }
+
+    /// Directory of files for the includes, which is made anew for every test
+    fn files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = temp_dir().join(format!("preprocessor-{test}-{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        for (path, text) in files {
+            let path = directory.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn object_macros() {
+        assert_eq!(preprocess("#define N 4\nint a = N;"), "int a = 4 ;");
+        assert_eq!(preprocess("#define A B\n#define B 1\nA"), "1");
+        assert_eq!(preprocess("#define N 4\n#undef N\nN"), "N");
+        // Macros do not expand within themselves, nor within string literals
+        assert_eq!(preprocess("#define x x + 1\nx \"x\""), "x + 1 \"x\"");
+    }
+
+    #[test]
+    fn function_macros() {
+        let text = "#define MAX(a, b) ((a) > (b) ? (a) : (b))\nMAX(1, f(2, 3))";
+        assert_eq!(preprocess(text), "((1) > (f(2, 3)) ? (1) : (f(2, 3)))");
+        // Names of function-like macros without arguments are left as they are
+        assert_eq!(preprocess("#define F(x) x\nF + F(2)"), "F + 2");
+        assert_eq!(preprocess("#define F() 1\nF()"), "1");
+        assert_eq!(preprocess("#define F (x) x\nF"), "(x) x");
+        // The expansion is rescanned together with the text that follows it
+        assert_eq!(preprocess("#define F G\n#define G(x) x+1\nF(2)"), "2+1");
+        let text = "#define F G\n#define G(x) H\n#define H(x) x*2\nF(1) (3);";
+        assert_eq!(preprocess(text), "3*2 ;");
+        assert_eq!(preprocess("#define F G\n#define G(x) x\nF + G"), "G + G");
+    }
+
+    #[test]
+    fn stringify_and_paste() {
+        let text = "#define S(x) #x\n#define CAT(a, b) a ## b\nS(a  \"b\") CAT(foo, 1)";
+        assert_eq!(preprocess(text), r#""a \"b\"" foo1"#);
+    }
+
+    #[test]
+    fn variadic_macros() {
+        // The spacing between the arguments is not kept
+        let text = "#define F(format, ...) printf(format, __VA_ARGS__)\nF(\"%d\", 1, (2, 3))";
+        assert_eq!(preprocess(text).replace(' ', ""), r#"printf("%d",1,(2,3))"#);
+        assert_eq!(preprocess("#define F(...) [__VA_ARGS__]\nF()"), "[]");
+    }
+
+    #[test]
+    fn conditions() {
+        let text =
+            "#define A 2\n#if A == 1\none\n#elif defined(A) && A > 1\ntwo\n#else\nthree\n#endif";
+        assert_eq!(preprocess(text), "two");
+        let text = "#ifdef B\nb\n#elif !defined B\nnot b\n#endif";
+        assert_eq!(preprocess(text), "not b");
+        // Names that are not macros count as zero
+        let text = "#if UNKNOWN\nyes\n#else\nno\n#endif";
+        assert_eq!(preprocess(text), "no");
+        let text = "#if 0\n#if 1\nnested\n#endif\n#else\nouter\n#endif";
+        assert_eq!(preprocess(text), "outer");
+    }
+
+    #[test]
+    fn include_order() {
+        let directory = files(
+            "include",
+            &[
+                ("main/a.h", "local"),
+                ("include/a.h", "system"),
+                ("include/b.h", "other"),
+            ],
+        );
+        let path = directory.join("main/main.c").display().to_string();
+        let includes = [directory.join("include")];
+        let includes: Vec<_> = includes.iter().map(|x| x.as_path()).collect();
+
+        // Quoted names are looked up next to the file first, angled ones only in the paths
+        let text = "#include \"a.h\"\n#include <a.h>\n#include \"b.h\"";
+        let (result, _) = run(&path, text, &includes, Vec::new());
+        assert_eq!(result.ok().as_deref(), Some("local system other"));
+
+        let (result, _) = run(&path, "#include <c.h>", &includes, Vec::new());
+        assert_eq!(result.err().map(|x| x.code), Some("E0112"));
+    }
+
+    #[test]
+    fn pragma_once() {
+        let directory = files(
+            "once",
+            &[("once.h", "#pragma once\nonce"), ("twice.h", "twice")],
+        );
+        let path = directory.join("main.c").display().to_string();
+        let text =
+            "#include \"once.h\"\n#include \"once.h\"\n#include \"twice.h\"\n#include \"twice.h\"";
+        let (result, _) = run(&path, text, &[], Vec::new());
+        assert_eq!(result.ok().as_deref(), Some("once twice twice"));
+    }
+
+    #[test]
+    fn command_line_defines() {
+        let defines = vec![
+            ("N".to_owned(), Some("1 + 2".to_owned())),
+            ("M".to_owned(), Some("3".to_owned())),
+            ("M".to_owned(), None),
+        ];
+        let (result, _) = run("main.c", "N M", &[], defines);
+        assert_eq!(result.ok().as_deref(), Some("1 + 2 M"));
+    }
+
+    #[test]
+    fn builtin_macros() {
+        assert_eq!(preprocess("a\n\n__LINE__ __FILE__"), "a 3 \"main.c\"");
+        let text = "#define LINE __LINE__\n\nLINE";
+        assert_eq!(preprocess(text), "3");
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("#if 1\nunterminated", "E0101"),
+            ("#define F(x) x\nF(1", "E0102"),
+            ("#endif", "E0103"),
+            ("#if 1\n#else\n#else\n#endif", "E0104"),
+            ("#error stop here", "E0105"),
+            ("#unknown", "E0106"),
+            ("#define 1", "E0107"),
+            ("#if defined(A\n#endif", "E0108"),
+            ("#if 1 +\n#endif", "E0109"),
+            ("#define F(x, x\n", "E0110"),
+            ("#include file", "E0111"),
+        ];
+        for (text, code) in cases {
+            let (result, _) = run("main.c", text, &[], Vec::new());
+            assert_eq!(result.err().map(|x| x.code), Some(code), "{text:?}");
+        }
+    }
+
+    /// Spans of the preprocessed text lead back into the files and the invocations
+    #[test]
+    fn spans() {
+        let header = "int x;\nint bad;\n";
+        let directory = files("spans", &[("header.h", header)]);
+        let path = directory.join("main.c").display().to_string();
+        let text = "#define ONE 1\n#include \"header.h\"\nint y = ONE;";
+        let (result, source) = run(&path, text, &[], Vec::new());
+        assert!(result.is_ok());
+
+        let start = source.text.find("bad").unwrap();
+        let span = source.span(start, start + 3);
+        let (file, contents) = source.file(span.file);
+        assert!(file.ends_with("header.h"), "{file}");
+        assert_eq!(&contents[span.start..span.end], "bad");
+
+        let start = source.text.find('1').unwrap();
+        let span = source.span(start, start + 1);
+        let (file, contents) = source.file(span.file);
+        assert_eq!(file, path);
+        assert_eq!(&contents[span.start..span.end], "ONE");
+
+        let start = source.text.find('y').unwrap();
+        let span = source.span(start, start + 1);
+        assert_eq!(&text[span.start..span.end], "y");
+    }
+
+    /// Code of a header is ordered at its include, ahead of the code that follows it
+    #[test]
+    fn positions() {
+        let directory = files("positions", &[("header.h", "int c;\n")]);
+        let path = directory.join("main.c").display().to_string();
+        let text = "int a;\n#include \"header.h\"\nint b;\n";
+        let (result, source) = run(&path, text, &[], Vec::new());
+        assert!(result.is_ok());
+
+        let span = |file, name| {
+            let start = source.text.find(name).unwrap();
+            let span = source.span(start, start + 1);
+            assert_eq!(span.file, file);
+            (start, source.position(span))
+        };
+        let (a, b, c) = (span(0, "a"), span(0, "b"), span(1, "c"));
+        assert_eq!([a.0, b.0, c.0], [a.1, b.1, c.1]);
+        assert!(a.1 < c.1 && c.1 < b.1);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::Span;
+use std::{
+    cmp::{max, min},
+    rc::Rc,
+};
+
+/// Text produced by the preprocessor along with where each part of it came from
+pub struct Source {
+    pub text: String,
+    files: Vec<File>,
+    segments: Vec<Segment>,
+}
+
+struct File {
+    path: String,
+    text: Rc<str>,
+}
+
+/// Part of the text starting at `start` that was taken from `offset` in a file
+struct Segment {
+    start: usize,
+    file: usize,
+    offset: usize,
+    /// Length of the macro invocation when the part is its expansion
+    expansion: Option<usize>,
+}
+
+impl Source {
+    pub fn new() -> Self {
+        Source {
+            text: String::new(),
+            files: Vec::new(),
+            segments: Vec::new(),
+        }
+    }
+
+    /// Registers a file, whose text is shared with the preprocessor while it reads the file
+    pub fn load(&mut self, path: &str, text: String) -> (usize, Rc<str>) {
+        let text: Rc<str> = text.into();
+        self.files.push(File {
+            path: path.to_owned(),
+            text: text.clone(),
+        });
+        (self.files.len() - 1, text)
+    }
+
+    pub fn path(&self, file: usize) -> &str {
+        &self.files[file].path
+    }
+
+    /// Appends text copied as is from the file at the offset
+    pub fn verbatim(&mut self, text: &str, file: usize, offset: usize) {
+        let continues = self.segments.last().is_some_and(|x| {
+            x.expansion.is_none()
+                && x.file == file
+                && x.offset + (self.text.len() - x.start) == offset
+        });
+
+        if !continues {
+            self.segments.push(Segment {
+                start: self.text.len(),
+                file,
+                offset,
+                expansion: None,
+            });
+        }
+        self.text += text;
+    }
+
+    /// Appends text produced by the macro invocation at the offset
+    pub fn expanded(&mut self, text: &str, file: usize, offset: usize, length: usize) {
+        self.segments.push(Segment {
+            start: self.text.len(),
+            file,
+            offset,
+            expansion: Some(length),
+        });
+        self.text += text;
+    }
+
+    /// File the span points into, along with its text
+    pub fn file(&self, file: usize) -> (&str, &str) {
+        let file = &self.files[file];
+        (&file.path, &file.text)
+    }
+
+    /// Traces a range of the text back to the file it came from,
+    /// expanded macros are traced back to their invocations
+    pub fn span(&self, start: usize, end: usize) -> Span {
+        let first = self.origin(start);
+        let last = self.origin(max(end, start + 1) - 1);
+        match first.file == last.file && last.end >= first.start {
+            true => first.to(last),
+            false => first,
+        }
+    }
+
+    /// Position in the text that the span's code ended up at, so that diagnostics can be
+    /// ordered the way the code is read, with the ones of a header at its `#include`
+    pub fn position(&self, span: Span) -> usize {
+        let mut segments = self.segments.iter().filter(|x| x.file == span.file);
+        let first = segments.clone().next();
+        // The parts of a file are in the order of their offsets, headers are placed between them
+        let segment = segments.rfind(|x| x.offset <= span.start).or(first);
+        match segment {
+            Some(x) if x.expansion.is_none() => x.start + span.start.saturating_sub(x.offset),
+            Some(x) => x.start,
+            None => 0,
+        }
+    }
+
+    /// Span of the file that the byte at the position of the text came from
+    fn origin(&self, position: usize) -> Span {
+        let index = self.segments.partition_point(|x| x.start <= position);
+        let segment = &self.segments[index.saturating_sub(1)];
+        let length = self.files[segment.file].text.len();
+        let (start, end) = match segment.expansion {
+            None => {
+                let offset = segment.offset + position - segment.start;
+                (offset, offset + 1)
+            }
+            Some(length) => (segment.offset, segment.offset + length),
+        };
+
+        Span {
+            file: segment.file,
+            start: min(start, length),
+            end: min(end, length),
+        }
+    }
+}
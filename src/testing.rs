@@ -0,0 +1,61 @@
+//! Runs the stages of the compiler on snippets of C, so the unit tests can inspect their results
+use crate::{
+    ast::Statement, context::Context, program::Program, syntax::SyntaxError, typed,
+    warning::Category, Analyzable, Assemblable, Checkable, ErrorLike, Target, Token, Tokenizable,
+    Translatable,
+};
+
+/// Tokens of the text, which must not have any lexical errors
+pub fn tokens(text: &str) -> Vec<Token<'_>> {
+    text.tokenize()
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|error| panic!("{} in {text:?}", error.message()))
+}
+
+pub fn parse(text: &str) -> (Vec<Statement<'_>>, Vec<SyntaxError<'_>>) {
+    tokens(text).into_iter().analyze()
+}
+
+/// Checks the text with every warning enabled, the text must not have any syntax errors
+pub fn check(text: &str) -> (Context<'_>, Vec<typed::Statement<'_>>) {
+    let (ast, errors) = parse(text);
+    if let Some(error) = errors.first() {
+        panic!("{} in {text:?}", error.message());
+    }
+
+    let mut context = Context::new(Category::ALL.into_iter().collect());
+    let typed = ast.check(&mut context).unwrap_or_default();
+    context.check_unused();
+    (context, typed)
+}
+
+/// Codes of the errors followed by the ones of the warnings, each in the order of reporting
+pub fn diagnostics(text: &str) -> Vec<&'static str> {
+    let (context, _) = check(text);
+    let errors = context.errors.iter().map(|x| x.code());
+    let warnings = context.warnings.iter().map(|x| x.code());
+    errors.chain(warnings).collect()
+}
+
+/// Assembly of the text for macOS, which must check without errors
+pub fn assemble(text: &str) -> String {
+    assemble_for(text, Target::MacOS)
+}
+
+pub fn assemble_for(text: &str, target: Target) -> String {
+    let (context, typed) = check(text);
+    if let Some(error) = context.errors.first() {
+        panic!("{} in {text:?}", error.message());
+    }
+
+    let mut program = Program::new(
+        context.globals,
+        context.locals,
+        context.storage,
+        context.qualifiers,
+    );
+    typed.translate(&mut program);
+    program
+        .assemble(target)
+        .unwrap_or_else(|error| panic!("{} in {text:?}", error.message()))
+}
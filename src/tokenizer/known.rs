@@ -1,17 +1,40 @@
-use lazy_static::lazy_static;
-use regex::Regex;
+pub const KEYWORDS: [&str; 18] = [
+    "typedef", "int", "float", "short", "long", "char", "for", "return", "switch", "case",
+    "default", "break", "goto", "sizeof", "const", "volatile", "static", "extern",
+];
 
-pub const KEYWORDS: [&str; 8] = [
-    "typedef", "int", "float", "short", "long", "char", "for", "return",
+/// Keywords of C that are reserved, but cannot be used yet
+pub const UNSUPPORTED: [&str; 26] = [
+    "auto",
+    "continue",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "if",
+    "inline",
+    "register",
+    "restrict",
+    "signed",
+    "struct",
+    "union",
+    "unsigned",
+    "void",
+    "while",
+    "_Alignas",
+    "_Alignof",
+    "_Atomic",
+    "_Bool",
+    "_Complex",
+    "_Generic",
+    "_Imaginary",
+    "_Noreturn",
+    "_Static_assert",
+    "_Thread_local",
 ];
 
-pub const SYMBOLS: [&str; 28] = [
+pub const SYMBOLS: [&str; 45] = [
     "[", "]", "{", "}", "(", ")", ";", "=", "+", "-", "*", "/", ",", ">", "<", ">=", "<=", "==",
-    "!=", "!", "&", "|", "^", "%", "&&", "||", "<<", ">>",
+    "!=", "!", "&", "|", "^", "%", "&&", "||", "<<", ">>", "+=", "-=", "*=", "/=", "%=", "&=",
+    "|=", "^=", "<<=", ">>=", "++", "--", "?", ":", "~", ".", "->",
 ];
-
-lazy_static! {
-    pub static ref TOKEN_EXPRESSION: Regex =
-        Regex::new(r"'[^']'|\/\/.*|(?s)\/\*.*?\*\/|\d+\.\d*|(?:>|<|=|!)=|&&|\|\||<<|>>|\w+|\S")
-            .unwrap();
-}
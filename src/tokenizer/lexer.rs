@@ -0,0 +1,152 @@
+use super::{known::SYMBOLS, to_token};
+use crate::{lex::LexError, preprocessor::Source, Span, Token};
+
+/// Splits the text into tokens one character at a time,
+/// skipping over whatever cannot be tokenized after reporting it
+pub struct Lexer<'a> {
+    text: &'a str,
+    position: usize,
+    /// Traces the spans back to the files, otherwise they are offsets into the text
+    source: Option<&'a Source>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(text: &'a str, source: Option<&'a Source>) -> Self {
+        Lexer {
+            text,
+            position: 0,
+            source,
+        }
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        match self.source {
+            Some(source) => source.span(start, end),
+            None => Span {
+                file: 0,
+                start,
+                end,
+            },
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    /// Moves past whitespace and comments, fails on a comment that never ends
+    fn skip(&mut self) -> Result<(), LexError> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                self.position += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                let Some(end) = trimmed.find("*/") else {
+                    let span = self.span(self.position, self.position + 2);
+                    self.position = self.text.len();
+                    return Err(LexError {
+                        code: "E0201",
+                        message: "Unterminated comment!".to_owned(),
+                        span: Some(span),
+                    });
+                };
+                self.position += end + 2;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Length of the token at the start of the text,
+/// or why it cannot be tokenized along with how much of the text to skip.
+/// The preprocessor splits its tokens the same way
+pub fn length(text: &str) -> Result<usize, (String, usize)> {
+    let mut chars = text.char_indices().peekable();
+    let (_, first) = chars.next().unwrap();
+    let second = text[first.len_utf8()..].chars().next();
+
+    Ok(match first {
+        x if x.is_alphabetic() || x == '_' => text
+            .find(|x: char| !x.is_alphanumeric() && x != '_')
+            .unwrap_or(text.len()),
+        // Numbers are taken whole and only then checked by `to_token`
+        x if x.is_ascii_digit() || x == '.' && second.is_some_and(|x| x.is_ascii_digit()) => {
+            let mut end = text.len();
+            while let Some((index, char)) = chars.next() {
+                match (char, chars.peek()) {
+                    ('e' | 'E' | 'p' | 'P', Some((_, '+' | '-'))) => {
+                        chars.next();
+                    }
+                    (x, _) if x.is_alphanumeric() || x == '_' || x == '.' => (),
+                    _ => {
+                        end = index;
+                        break;
+                    }
+                }
+            }
+            end
+        }
+        quote @ ('"' | '\'') => {
+            while let Some((index, char)) = chars.next() {
+                match char {
+                    '\\' => {
+                        chars.next_if(|x| x.1 != '\n');
+                    }
+                    '\n' => break,
+                    x if x == quote => return Ok(index + 1),
+                    _ => (),
+                }
+            }
+
+            let kind = match quote {
+                '"' => "string",
+                _ => "character",
+            };
+            let message = format!("Unterminated {} literal!", kind);
+            return Err((message, text.find('\n').unwrap_or(text.len())));
+        }
+        // The longest symbol that matches wins
+        x => match (1..=3)
+            .rev()
+            .find(|&x| text.get(..x).is_some_and(|x| SYMBOLS.contains(&x)))
+        {
+            Some(length) => length,
+            None => return Err((format!("Unexpected character '{}'!", x), x.len_utf8())),
+        },
+    })
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(error) = self.skip() {
+            return Some(Err(error));
+        }
+
+        let rest = self.rest();
+        if rest.is_empty() {
+            return None;
+        }
+
+        let start = self.position;
+        Some(match length(rest) {
+            Ok(length) => {
+                self.position += length;
+                to_token(&rest[..length], self.span(start, self.position))
+            }
+            Err((message, length)) => {
+                self.position += length;
+                Err(LexError {
+                    code: "E0202",
+                    message,
+                    span: Some(self.span(start, self.position)),
+                })
+            }
+        })
+    }
+}
@@ -0,0 +1,277 @@
+use crate::types::Literal;
+
+/// Classifies a numeric constant by its form and suffix, the way C types them
+pub fn number(text: &str) -> Result<Literal, &'static str> {
+    let text = text.to_ascii_lowercase();
+    let hexadecimal = text.starts_with("0x");
+    let floating = match hexadecimal {
+        true => text.contains(['.', 'p']),
+        false => text.contains(['.', 'e']),
+    };
+
+    match floating {
+        true => float(&text, hexadecimal),
+        false => integer(&text),
+    }
+}
+
+fn integer(text: &str) -> Result<Literal, &'static str> {
+    let (digits, radix) = if let Some(digits) = text.strip_prefix("0x") {
+        (digits, 16)
+    } else if let Some(digits) = text.strip_prefix("0b") {
+        (digits, 2)
+    } else if text.len() > 1 && text.starts_with('0') {
+        (&text[1..], 8)
+    } else {
+        (text, 10)
+    };
+
+    let end = digits
+        .find(|x: char| !x.is_digit(radix))
+        .unwrap_or(digits.len());
+    let (digits, suffix) = digits.split_at(end);
+    if suffix.starts_with(|x: char| x.is_ascii_digit()) {
+        return Err("Digits of the integer literal are not valid for its base!");
+    }
+    if !["", "u", "l", "ul", "lu", "ll", "ull", "llu"].contains(&suffix) {
+        return Err("Integer literal has an invalid suffix!");
+    }
+
+    // A lone `0` is an octal prefix with no digits after it
+    let value = match (digits, radix) {
+        ("", 8) => 0,
+        (digits, radix) => match u64::from_str_radix(digits, radix) {
+            Ok(value) => value,
+            Err(_) if digits.is_empty() => return Err("Integer literal has no digits!"),
+            Err(_) => return Err("Integer literal does not fit into 64 bits!"),
+        },
+    };
+
+    let unsigned = suffix.contains('u');
+    let long = suffix.contains('l');
+    match value {
+        x if !long && x <= i32::MAX as u64 => Ok(Literal::Integer(x as i32)),
+        x if x <= i64::MAX as u64 => Ok(Literal::Long(x as i64)),
+        // Without unsigned types the bits are kept as they are
+        x if unsigned || radix != 10 => Ok(Literal::Long(x as i64)),
+        _ => Err("Integer literal does not fit into a long!"),
+    }
+}
+
+fn float(text: &str, hexadecimal: bool) -> Result<Literal, &'static str> {
+    // Hexadecimal digits include `f`, but their exponent is mandatory and comes first
+    let body = match text.strip_suffix(['f', 'l']) {
+        Some(body) if !hexadecimal || body.contains('p') => body,
+        _ => text,
+    };
+
+    let value = match hexadecimal {
+        true => hexadecimal_float(&body[2..]),
+        false => body.parse::<f32>().ok(),
+    };
+
+    match value {
+        Some(x) if x.is_finite() => Ok(Literal::Floating(x)),
+        Some(_) => Err("Floating literal is out of the range of a float!"),
+        None => Err("Floating literal is malformed!"),
+    }
+}
+
+/// Value of `mantissa p exponent` where the mantissa is hexadecimal and the exponent is binary
+fn hexadecimal_float(text: &str) -> Option<f32> {
+    let (mantissa, exponent) = text.split_once('p')?;
+    let exponent = exponent.parse::<i32>().ok()?;
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+
+    let mut value = 0.0;
+    for digit in whole.chars().chain(fraction.chars()) {
+        value = value * 16.0 + digit.to_digit(16)? as f64;
+    }
+    let exponent = exponent.checked_sub(4 * fraction.len() as i32)?;
+    Some((value * 2f64.powi(exponent)) as f32)
+}
+
+pub fn character(text: &str) -> Result<Literal, &'static str> {
+    match unescape(text).as_deref() {
+        Some(&[byte]) => Ok(Literal::Character(byte as char)),
+        Some(_) => Err("Character literal must contain exactly one character!"),
+        None => Err("Character literal has an invalid escape sequence!"),
+    }
+}
+
+/// Resolves the escape sequences of a quoted literal into its bytes,
+/// returns `None` when one of them is not valid
+pub fn unescape(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut chars = text[1..text.len() - 1].chars().peekable();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(char.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+
+        let byte = match chars.next()? {
+            'n' => b'\n',
+            't' => b'\t',
+            'r' => b'\r',
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0c,
+            'v' => 0x0b,
+            x @ ('\\' | '\'' | '"' | '?') => x as u8,
+            'x' => {
+                let mut value: u32 = 0;
+                let mut digits = 0;
+                while let Some(digit) = chars.peek().and_then(|x| x.to_digit(16)) {
+                    value = value.checked_mul(16)? + digit;
+                    digits += 1;
+                    chars.next();
+                }
+                if digits == 0 || value > 0xff {
+                    return None;
+                }
+                value as u8
+            }
+            x @ '0'..='7' => {
+                let mut value = x.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match chars.peek().and_then(|x| x.to_digit(8)) {
+                        Some(digit) => value = value * 8 + digit,
+                        None => break,
+                    }
+                    chars.next();
+                }
+                if value > 0xff {
+                    return None;
+                }
+                value as u8
+            }
+            _ => return None,
+        };
+        bytes.push(byte);
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Literal::*;
+
+    #[test]
+    fn integers() {
+        let cases = [
+            ("0", Integer(0)),
+            ("42", Integer(42)),
+            ("0x7fffffff", Integer(i32::MAX)),
+            ("0x80000000", Long(0x80000000)),
+            ("2147483647", Integer(i32::MAX)),
+            ("2147483648", Long(2147483648)),
+            ("42l", Long(42)),
+            ("42ULL", Long(42)),
+            ("017", Integer(15)),
+            ("0b101", Integer(5)),
+            ("0XfF", Integer(255)),
+            ("9223372036854775807", Long(i64::MAX)),
+            ("0xffffffffffffffff", Long(-1)),
+            ("18446744073709551615u", Long(-1)),
+        ];
+        for (text, literal) in cases {
+            assert_eq!(number(text), Ok(literal), "{text}");
+        }
+    }
+
+    #[test]
+    fn floats() {
+        let cases = [
+            ("1.5", 1.5),
+            ("1.", 1.0),
+            (".25", 0.25),
+            ("1e3", 1000.0),
+            ("2.5E-1f", 0.25),
+            ("1.0L", 1.0),
+            ("0x1p4", 16.0),
+            ("0x1.8p1", 3.0),
+            ("0x.8p0f", 0.5),
+        ];
+        for (text, value) in cases {
+            assert_eq!(number(text), Ok(Floating(value)), "{text}");
+        }
+    }
+
+    #[test]
+    fn malformed_numbers() {
+        let cases = [
+            ("0b", "Integer literal has no digits!"),
+            ("0x", "Integer literal has no digits!"),
+            (
+                "08",
+                "Digits of the integer literal are not valid for its base!",
+            ),
+            (
+                "0b102",
+                "Digits of the integer literal are not valid for its base!",
+            ),
+            ("1e", "Floating literal is malformed!"),
+            ("1e+", "Floating literal is malformed!"),
+            ("0x1.8", "Floating literal is malformed!"),
+            ("1e39", "Floating literal is out of the range of a float!"),
+            ("12abc", "Integer literal has an invalid suffix!"),
+            ("1lul", "Integer literal has an invalid suffix!"),
+            (
+                "18446744073709551616",
+                "Integer literal does not fit into 64 bits!",
+            ),
+            (
+                "9223372036854775808",
+                "Integer literal does not fit into a long!",
+            ),
+        ];
+        for (text, message) in cases {
+            assert_eq!(number(text), Err(message), "{text}");
+        }
+    }
+
+    #[test]
+    fn characters() {
+        let cases = [
+            ("'a'", Ok(Character('a'))),
+            ("'\\n'", Ok(Character('\n'))),
+            ("'\\0'", Ok(Character('\0'))),
+            ("'\\x41'", Ok(Character('A'))),
+            ("'\\101'", Ok(Character('A'))),
+            (
+                "'ab'",
+                Err("Character literal must contain exactly one character!"),
+            ),
+            (
+                "''",
+                Err("Character literal must contain exactly one character!"),
+            ),
+            (
+                "'\\q'",
+                Err("Character literal has an invalid escape sequence!"),
+            ),
+            (
+                "'\\x100'",
+                Err("Character literal has an invalid escape sequence!"),
+            ),
+        ];
+        for (text, literal) in cases {
+            assert_eq!(character(text), literal, "{text}");
+        }
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(unescape(r#""a\tb""#), Some(b"a\tb".to_vec()));
+        assert_eq!(unescape(r#""\777""#), None);
+        assert_eq!(unescape(r#""\x""#), None);
+        assert_eq!(unescape(r#""\"\\""#), Some(b"\"\\".to_vec()));
+    }
+}
@@ -1,34 +1,49 @@
 mod known;
+mod lexer;
+mod literal;
 
-use self::known::*;
-use crate::types::*;
+use self::{known::*, lexer::Lexer, literal::*};
+use crate::{lex::LexError, preprocessor::Source, types::*};
 
-fn to_token<'a>(text: &'a str) -> Token<'a> {
-    match text {
-        x if KEYWORDS.contains(&x) => Token::Keyword(x),
-        x if SYMBOLS.contains(&x) => Token::Symbol(x),
-        x if x.parse::<i32>().is_ok() => Token::Data(Literal::Integer(x.parse().unwrap()), x),
-        x if x.parse::<i64>().is_ok() => Token::Data(Literal::Long(x.parse().unwrap()), x),
-        x if x.parse::<f32>().is_ok() => Token::Data(Literal::Floating(x.parse().unwrap()), x),
-        x if x.len() == 3 && x.trim_matches('\'').len() == 1 => {
-            Token::Data(Literal::Character(x.chars().nth(1).unwrap()), x)
+pub use known::UNSUPPORTED;
+pub use lexer::length;
+pub use literal::unescape;
+
+fn to_token<'a>(text: &'a str, span: Span) -> Result<Token<'a>, LexError> {
+    let invalid = |message: &str| LexError {
+        code: "E0203",
+        message: message.to_owned(),
+        span: Some(span),
+    };
+
+    Ok(match text {
+        x if KEYWORDS.contains(&x) || UNSUPPORTED.contains(&x) => Token::Keyword(x, span),
+        x if SYMBOLS.contains(&x) => Token::Symbol(x, span),
+        x if x.starts_with(|x: char| x.is_ascii_digit() || x == '.') => {
+            Token::Data(number(x).map_err(invalid)?, x, span)
         }
-        x if x.starts_with("//") => Token::Comment,
-        x if x.starts_with("/*") => Token::Comment,
-        x => Token::Identifier(x),
-    }
+        x if x.starts_with('\'') => Token::Data(character(x).map_err(invalid)?, x, span),
+        x if x.starts_with('"') => match unescape(x) {
+            Some(_) => Token::Data(Literal::String, x, span),
+            None => return Err(invalid("String literal has an invalid escape sequence!")),
+        },
+        x => Token::Identifier(x, span),
+    })
 }
 
 pub trait Tokenizable {
-    fn tokenize(&self) -> impl TokenStream;
+    fn tokenize(&self) -> Lexer<'_>;
 }
 
 impl Tokenizable for str {
-    fn tokenize(&self) -> impl TokenStream {
-        TOKEN_EXPRESSION
-            .captures_iter(self)
-            .map(|x| x.extract::<0>().0)
-            .map(to_token)
-            .filter(|x| !matches!(x, Token::Comment))
+    fn tokenize(&self) -> Lexer<'_> {
+        Lexer::new(self, None)
+    }
+}
+
+/// Tokens of the preprocessed text have spans in the files it came from
+impl Tokenizable for Source {
+    fn tokenize(&self) -> Lexer<'_> {
+        Lexer::new(&self.text, Some(self))
     }
 }
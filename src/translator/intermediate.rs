@@ -1,6 +1,7 @@
 use crate::{
     ast::{Data, Primitive},
     program::Program,
+    Span,
 };
 use std::{cmp::min, fmt::Debug};
 
@@ -20,15 +21,27 @@ pub enum Operation {
     Div,
     Mul,
     Neg,
+    Mvn,
     Cmp,
     CSet,
+    CSel,
     And,
     Ret,
     SCvtF,
     FCvtZS,
+    SxtW,
+    SxtH,
+    SxtB,
+    Narrow,
+    Adr,
+    Adg,
     Lbl,
     BEq,
+    BNe,
+    BLt,
+    BHi,
     B,
+    Br,
 }
 
 #[derive(Clone, PartialEq)]
@@ -36,6 +49,7 @@ pub enum Operand {
     Identifier(String, usize),
     Asm(&'static str),
     Address(usize),
+    Pointer(usize, Primitive),
     Label(String),
     Data(Data),
     Temp,
@@ -46,12 +60,15 @@ pub struct Instruction {
     pub operation: Operation,
     pub operand1: Operand,
     pub operand2: Operand,
+    /// Span of the statement the instruction was translated from
+    pub span: Span,
 }
 
 impl Debug for Operand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Operand::Address(x) => write!(f, "@{}", x),
+            Operand::Pointer(x, _) => write!(f, "[@{}]", x),
             Operand::Identifier(x, 0) => write!(f, "'{x}'"),
             Operand::Identifier(x, i) => write!(f, "'{x}[{i}]'"),
             Operand::Data(Data::Float(x)) => write!(f, "{:e}", x),
@@ -78,25 +95,33 @@ pub const BYTE: Operand = Operand::Data(Data::Integer(255));
 pub const ZERO: Operand = Operand::Data(Data::Integer(0));
 
 impl Operand {
-    pub fn datatype<'a>(&self, program: &'a Program) -> Option<Primitive> {
+    pub fn datatype(&self, program: &Program) -> Option<Primitive> {
         match self {
             Self::Address(x) => program.instructions[*x].datatype(program),
             Self::Identifier(identifier, _) => program.type_of(identifier),
-            Self::Data(Data::Integer(_)) => Some(Primitive::Int),
-            Self::Data(Data::Float(_)) => Some(Primitive::Float),
-            Self::Data(Data::Short(_)) => Some(Primitive::Short),
-            Self::Data(Data::Byte(_)) => Some(Primitive::Byte),
-            Self::Data(Data::Long(_)) => Some(Primitive::Long),
+            Self::Pointer(_, datatype) => Some(*datatype),
+            Self::Data(data) => Some(data.datatype()),
             _ => None,
         }
     }
+
+    /// Whether the operand reads the result of the instruction at `address`
+    pub fn uses(&self, address: usize) -> bool {
+        match self {
+            Self::Address(x) | Self::Pointer(x, _) => *x == address,
+            _ => false,
+        }
+    }
 }
 
 impl Instruction {
-    pub fn datatype<'a>(&self, program: &'a Program) -> Option<Primitive> {
+    pub fn datatype(&self, program: &Program) -> Option<Primitive> {
         match self.operation {
             Operation::SCvtF => return Some(Primitive::Float),
-            Operation::FCvtZS => return Some(Primitive::Int),
+            Operation::FCvtZS | Operation::Narrow => return Some(Primitive::Int),
+            Operation::SxtH => return Some(Primitive::Short),
+            Operation::SxtB => return Some(Primitive::Byte),
+            Operation::SxtW | Operation::Adr | Operation::Adg => return Some(Primitive::Long),
             Operation::CSet => return Some(Primitive::Int),
             _ => (),
         }
@@ -105,9 +130,9 @@ impl Instruction {
         let type2 = self.operand2.datatype(program);
 
         match (type1, type2) {
-            (Some(x), Some(y)) if x == y => return Some(x),
-            (Some(x), None) => return Some(x),
-            (None, Some(x)) => return Some(x),
+            (Some(x), Some(y)) if x == y => Some(x),
+            (Some(x), None) => Some(x),
+            (None, Some(x)) => Some(x),
             _ => min(type1, type2), // downcast
         }
     }
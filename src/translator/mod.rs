@@ -2,326 +2,606 @@ pub mod intermediate;
 pub mod program;
 
 use crate::{
-    ast::{
-        Assignment, BinaryOperator, Data, Expression, Loop, Primitive, Statement, UnaryOperator,
-        Value, Variable,
-    },
-    semantic::SemanticError,
-    types::ast::Initializer,
+    ast::{BinaryOperator, Compound, Data, Primitive, UnaryOperator, UpdateOperator},
+    typed::{Expression, Initialization, Kind, Loop, Place, Statement, Switch},
 };
 use intermediate::{Operand, Operation, BYTE, ZERO};
 use program::Program;
-use std::cmp::max;
 
-pub trait Translatable<'a> {
-    fn translate(self, program: &mut Program<'a>) -> Result<(), SemanticError<'a>>;
+/// Translation of the typed tree into the intermediate representation,
+/// which cannot fail as the tree is already checked
+pub trait Translatable {
+    fn translate(self, program: &mut Program);
 }
 
-impl<'a> Translatable<'a> for Statement<'a> {
-    fn translate(self, program: &mut Program<'a>) -> Result<(), SemanticError<'a>> {
+pub trait Evaluable {
+    fn evaluate(self, program: &mut Program) -> Operand;
+}
+
+pub trait Locatable {
+    fn locate(self, program: &mut Program) -> Operand;
+}
+
+impl<'a> Translatable for Statement<'a> {
+    fn translate(self, program: &mut Program) {
         match self {
-            Self::Type(typedef) => program.define_type(&typedef.name, typedef.datatype),
-            Self::Assignment(assignment) => assignment.translate(program),
-            Self::Variable(variable) => variable.translate(program),
-            Self::Loop(repetition) => repetition.translate(program),
-            Self::Return(value) => {
-                if program.toplevel() {
-                    return Err(SemanticError {
-                        message: "Return is not allowed on the top-level!".to_owned(),
-                        token: None,
-                    });
+            Self::Initializations(initializations, _) => {
+                for initialization in initializations {
+                    initialization.translate(program);
                 }
-
-                value.translate(program)?;
-                let operand = program.cast(program.last(), Some(Primitive::Int));
-                program.instruct(Operation::Ret, operand, Operand::None);
-                Ok(())
             }
-            Self::Function(function) => {
-                if function.name != "main" {
-                    return Err(SemanticError {
-                        message: "Only 'main' function is supported by this implementation!"
-                            .to_owned(),
-                        token: Some(&function.name),
-                    });
+            Self::Loop(repetition, _) => repetition.translate(program),
+            Self::Switch(selection, _) => selection.translate(program),
+            Self::Function(body, _) => body.translate(program),
+            // The checker only leaves the cases that are within a switch, which gave them labels
+            Self::Case(..) => {
+                if let Some(label) = program.next_case() {
+                    program.instruct(Operation::Lbl, Operand::Label(label), Operand::None);
                 }
-
-                program.push_scope();
-                function.body.translate(program)?;
-                program.pop_scope();
-                Ok(())
             }
-            Self::Noop => Ok(()),
+            Self::Break(_) => {
+                let label = program.break_label().unwrap();
+                program.instruct(Operation::B, Operand::Label(label), Operand::None);
+            }
+            Self::Expression(expression, _) => {
+                expression.evaluate(program);
+            }
+            Self::Return(value, _) => {
+                let operand = value.evaluate(program);
+                program.instruct(Operation::Ret, operand, Operand::None);
+            }
+            Self::Label(name, _) => {
+                let label = Operand::Label(format!("label_{}", name));
+                program.instruct(Operation::Lbl, label, Operand::None);
+            }
+            Self::Goto(name, _) => {
+                let label = Operand::Label(format!("label_{}", name));
+                program.instruct(Operation::B, label, Operand::None);
+            }
         }
     }
 }
 
-impl<'a> Translatable<'a> for Assignment<'a> {
-    fn translate(self, program: &mut Program<'a>) -> Result<(), SemanticError<'a>> {
-        if program.toplevel() {
-            return Err(SemanticError {
-                message: "Assignments are not allowed on the top-level!".to_owned(),
-                token: Some(self.identifier.0),
-            });
+impl Translatable for Initialization {
+    fn translate(self, program: &mut Program) {
+        for (index, expression) in self.values.into_iter().enumerate() {
+            let value = expression.evaluate(program);
+            let location = Operand::Identifier(self.symbol.identifier.clone(), index);
+            program.store(location, value);
         }
+    }
+}
 
-        let values = match self.value {
-            Initializer::Expression(value) => vec![value],
-            Initializer::List(values) => values,
-        };
+impl<'a> Translatable for Loop<'a> {
+    fn translate(self, program: &mut Program) {
+        let loop_start = program.generate_label("loop_start");
+        let loop_end = program.generate_label("loop_end");
 
-        for (index, expression) in values.into_iter().enumerate() {
-            expression.translate(program)?;
-            let identifier = program.infer_name(&self.identifier.0)?;
-            let value = program.cast(program.last(), program.type_of(&identifier));
-            program.instruct(
-                if program.is_global(&self.identifier.0)? {
-                    Operation::Stg
-                } else {
-                    Operation::Str
-                },
-                Operand::Identifier(
-                    program.infer_name(self.identifier.0)?,
-                    index + self.identifier.1,
-                ),
-                value,
-            );
+        if let Some(initialization) = self.initialization {
+            initialization.translate(program);
         }
-        Ok(())
+        program.instruct(
+            Operation::Lbl,
+            Operand::Label(loop_start.clone()),
+            Operand::None,
+        );
+
+        let condition = self.condition.evaluate(program);
+        program.instruct(Operation::Cmp, condition, Operand::Data(Data::Integer(0)));
+        program.instruct(
+            Operation::BEq,
+            Operand::Label(loop_end.clone()),
+            Operand::None,
+        );
+
+        program.push_break(loop_end.clone());
+        self.body.translate(program);
+        program.pop_break();
+        self.increment.evaluate(program);
+
+        program.instruct(Operation::B, Operand::Label(loop_start), Operand::None);
+        program.instruct(Operation::Lbl, Operand::Label(loop_end), Operand::None);
     }
 }
 
-impl<'a> Translatable<'a> for Variable<'a> {
-    fn translate(self, program: &mut Program<'a>) -> Result<(), SemanticError<'a>> {
-        if program.toplevel() {
-            match self.assignment {
-                Some(Assignment {
-                    identifier: (name, _),
-                    value: Initializer::Expression(Expression::Value(Value::Data(data))),
-                }) => program.define_variable(name, self.datatype, vec![data])?,
-                Some(Assignment {
-                    identifier: (name, _),
-                    value: Initializer::List(values),
-                }) => {
-                    let data: Vec<_> = (&values)
-                        .iter()
-                        .filter_map(|value| match value {
-                            Expression::Value(Value::Data(data)) => Some(*data),
-                            _ => None,
-                        })
-                        .collect();
-
-                    if data.len() != values.len() {
-                        return Err(SemanticError {
-                            message: format!(
-                                "Top-level variable's '{}' initialization list cannot contain expressions!",
-                                self.name
-                            ),
-                            token: Some(self.name),
-                        });
-                    }
+impl<'a> Translatable for Switch<'a> {
+    fn translate(self, program: &mut Program) {
+        let value = self.value.evaluate(program);
 
-                    program.define_variable(name, self.datatype, data)?
-                }
-                Some(_) | None => {
-                    return Err(SemanticError {
-                        message: format!(
-                            "Top-level variable '{}' must be initialized with a constant value!",
-                            self.name
-                        ),
-                        token: Some(self.name),
-                    })
-                }
-            }
-        } else {
-            program.define_variable(&self.name, self.datatype, vec![])?;
-            if let Some(assignment) = self.assignment {
-                assignment.translate(program)?;
+        let end = program.generate_label("switch_end");
+        let mut fallback = end.clone();
+        let mut labels = Vec::new();
+        let mut cases: Vec<(i64, String)> = Vec::new();
+
+        let mut values = Vec::new();
+        collect_cases(&self.body, &mut values);
+        for value in values {
+            let label = program.generate_label("case");
+            labels.push(label.clone());
+            match value {
+                Some(value) => cases.push((value, label)),
+                None => fallback = label,
             }
         }
 
-        Ok(())
+        cases.sort();
+        dispatch(program, value, &cases, &fallback);
+
+        program.push_break(end.clone());
+        program.push_cases(labels);
+        for statement in self.body {
+            program.span = statement.span();
+            statement.translate(program);
+        }
+        program.pop_cases();
+        program.pop_break();
+        program.instruct(Operation::Lbl, Operand::Label(end), Operand::None);
     }
 }
 
-impl<'a> Translatable<'a> for Loop<'a> {
-    fn translate(self, program: &mut Program<'a>) -> Result<(), SemanticError<'a>> {
-        if program.toplevel() {
-            return Err(SemanticError {
-                message: "Loops are not allowed on the top-level!".to_owned(),
-                token: Some(self.initialization.name),
-            });
+/// Values of the switch's cases in the order they are translated, along with the ones
+/// in its loops. Nested switches have cases of their own
+fn collect_cases(body: &[Statement], values: &mut Vec<Option<i64>>) {
+    for statement in body {
+        match statement {
+            Statement::Case(value, _) => values.push(*value),
+            Statement::Loop(repetition, _) => collect_cases(&repetition.body, values),
+            _ => (),
         }
-        program.push_scope();
-
-        let loop_start = program.generate_label("loop_start");
-        let loop_end = program.generate_label("loop_end");
+    }
+}
 
-        self.initialization.translate(program)?;
+/// Jumps to the label of the matching case, dense ranges go through a jump table
+fn dispatch(program: &mut Program, value: Operand, cases: &[(i64, String)], fallback: &str) {
+    let (Some((min, _)), Some((max, _))) = (cases.first(), cases.last()) else {
         program.instruct(
-            Operation::Lbl,
-            Operand::Label(loop_start.clone()),
+            Operation::B,
+            Operand::Label(fallback.to_owned()),
             Operand::None,
         );
+        return;
+    };
+
+    // Ranges too wide for a long to hold are never dense enough for a table
+    let range = max.checked_sub(*min).and_then(|x| x.checked_add(1));
+    let Some(range) = range.filter(|&x| cases.len() >= 4 && x <= 3 * cases.len() as i64) else {
+        return tree(program, value, cases, fallback);
+    };
+
+    // Values out of range wrap around to large unsigned numbers, so one check covers both ends
+    let index = match min {
+        0 => value,
+        _ => {
+            let min = constant(program, *min, &value);
+            program.instruct(Operation::Sub, value, min);
+            program.last()
+        }
+    };
+    let bound = constant(program, range - 1, &index);
+    program.instruct(Operation::Cmp, index.clone(), bound);
+    program.instruct(
+        Operation::BHi,
+        Operand::Label(fallback.to_owned()),
+        Operand::None,
+    );
 
-        self.condition.translate(program)?;
+    // Every table entry is a single 4 byte branch instruction
+    let table = program.generate_label("jump_table");
+    let index = program.convert(index, Some(Primitive::Long));
+    program.instruct(Operation::Adr, Operand::Temp, Operand::Label(table.clone()));
+    let base = program.last();
+    program.instruct(Operation::Lsl, index, Operand::Data(Data::Long(2)));
+    program.instruct(Operation::Add, base, program.last());
+    program.instruct(Operation::Br, program.last(), Operand::None);
+
+    program.instruct(Operation::Lbl, Operand::Label(table), Operand::None);
+    let mut cases = cases.iter().peekable();
+    for key in *min..=*max {
+        let label = match cases.next_if(|(x, _)| *x == key) {
+            Some((_, label)) => label.as_str(),
+            None => fallback,
+        };
         program.instruct(
-            Operation::Cmp,
-            program.last(),
-            Operand::Data(Data::Integer(0)),
+            Operation::B,
+            Operand::Label(label.to_owned()),
+            Operand::None,
         );
+    }
+}
+
+/// Binary search over the sorted cases
+fn tree(program: &mut Program, value: Operand, cases: &[(i64, String)], fallback: &str) {
+    if cases.len() <= 3 {
+        for (key, label) in cases {
+            let key = constant(program, *key, &value);
+            program.instruct(Operation::Cmp, value.clone(), key);
+            program.instruct(Operation::BEq, Operand::Label(label.clone()), Operand::None);
+        }
         program.instruct(
-            Operation::BEq,
-            Operand::Label(loop_end.clone()),
+            Operation::B,
+            Operand::Label(fallback.to_owned()),
             Operand::None,
         );
+        return;
+    }
 
-        self.body.translate(program)?;
-        self.increment.translate(program)?;
+    let middle = cases.len() / 2;
+    let (key, label) = &cases[middle];
+    let lower = program.generate_label("case_lower");
 
-        program.instruct(Operation::B, Operand::Label(loop_start), Operand::None);
-        program.instruct(Operation::Lbl, Operand::Label(loop_end), Operand::None);
-        program.pop_scope();
+    let key = constant(program, *key, &value);
+    program.instruct(Operation::Cmp, value.clone(), key);
+    program.instruct(Operation::BEq, Operand::Label(label.clone()), Operand::None);
+    program.instruct(Operation::BLt, Operand::Label(lower.clone()), Operand::None);
+
+    tree(program, value.clone(), &cases[middle + 1..], fallback);
+    program.instruct(Operation::Lbl, Operand::Label(lower), Operand::None);
+    tree(program, value, &cases[..middle], fallback);
+}
+
+/// Constant of the operand's type, that is moved into a register
+/// when it does not fit into an arithmetic immediate
+fn constant(program: &mut Program, value: i64, operand: &Operand) -> Operand {
+    let data = match operand.datatype(program) {
+        Some(Primitive::Long) => Data::Long(value),
+        _ => Data::Integer(value as i32),
+    };
 
-        Ok(())
+    if value.unsigned_abs() < 4096 {
+        return Operand::Data(data);
     }
+    program.instruct(Operation::Mov, Operand::Temp, Operand::Data(data));
+    program.last()
 }
 
-impl<'a> Translatable<'a> for Expression<'a> {
-    fn translate(self, program: &mut Program<'a>) -> Result<(), SemanticError<'a>> {
-        match self {
-            Self::Value(value) => {
-                match value {
-                    Value::Data(data) => {
-                        program.instruct(Operation::Mov, Operand::Temp, Operand::Data(data));
-                    }
-                    Value::Pointer(identifier, index) => {
-                        program.instruct(
-                            if program.is_global(identifier)? {
-                                Operation::Ldg
-                            } else {
-                                Operation::Ldr
-                            },
-                            Operand::Temp,
-                            Operand::Identifier(program.infer_name(identifier)?, index),
-                        );
-                    }
+impl Evaluable for Expression {
+    fn evaluate(self, program: &mut Program) -> Operand {
+        match self.kind {
+            Kind::Data(data) => {
+                program.instruct(Operation::Mov, Operand::Temp, Operand::Data(data));
+            }
+            Kind::Load(place) => {
+                let location = place.locate(program);
+                program.load(location);
+            }
+            // Strings are placed into read-only data and evaluate to their address
+            Kind::String(mut bytes) => {
+                bytes.push(0);
+                let datatype = Compound(Primitive::Byte, bytes.len());
+                let values = bytes.into_iter().map(|x| Data::Byte(x as i8)).collect();
+                let name = program.define_constant(".str", datatype, values);
+                program.instruct(Operation::Adg, Operand::Temp, Operand::Identifier(name, 0));
+            }
+            Kind::Assignment { op, target, value } => {
+                let location = target.locate(program);
+                let datatype = value.datatype;
+                let mut value = value.evaluate(program);
+                // Compound assignments reuse the located target, so it is evaluated only once
+                if let Some(op) = op {
+                    let current = program.load(location.clone());
+                    let current = program.convert(current, Some(datatype));
+                    value = binary(program, op, current, value);
+                    value = program.convert(value, Some(self.datatype));
+                }
+                return program.store(location, value);
+            }
+            Kind::Update {
+                op,
+                target,
+                postfix,
+            } => {
+                let location = target.locate(program);
+                let current = program.load(location.clone());
+                let step = match self.datatype {
+                    Primitive::Float => Data::Float(1.0),
+                    Primitive::Long => Data::Long(1),
+                    _ => Data::Integer(1),
+                };
+                program.instruct(Operation::Mov, Operand::Temp, Operand::Data(step));
+
+                let op = match op {
+                    UpdateOperator::Increment => BinaryOperator::Addition,
+                    UpdateOperator::Decrement => BinaryOperator::Subtraction,
                 };
+                let value = binary(program, op, current.clone(), program.last());
+                let value = program.store(location, value);
+                return if postfix { current } else { value };
+            }
+            Kind::Conditional {
+                condition,
+                then,
+                otherwise,
+            } if then.pure() && otherwise.pure() => {
+                // Both arms are safe to evaluate, so the result is picked without branching
+                let then = then.evaluate(program);
+                let otherwise = otherwise.evaluate(program);
+
+                let condition = condition.evaluate(program);
+                program.instruct(Operation::Cmp, condition, ZERO);
+                program.instruct(Operation::CSel, then, otherwise);
+            }
+            Kind::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let result = program.temporary(self.datatype);
+                let alternative = program.generate_label("alternative");
+                let end = program.generate_label("conditional_end");
+
+                let condition = condition.evaluate(program);
+                program.instruct(Operation::Cmp, condition, ZERO);
+                program.instruct(
+                    Operation::BEq,
+                    Operand::Label(alternative.clone()),
+                    Operand::None,
+                );
+
+                let then = then.evaluate(program);
+                program.store(result.clone(), then);
+                program.instruct(Operation::B, Operand::Label(end.clone()), Operand::None);
+
+                program.instruct(Operation::Lbl, Operand::Label(alternative), Operand::None);
+                let otherwise = otherwise.evaluate(program);
+                program.store(result.clone(), otherwise);
+
+                program.instruct(Operation::Lbl, Operand::Label(end), Operand::None);
+                program.load(result);
+            }
+            Kind::Comma { lhs, rhs } => {
+                lhs.evaluate(program);
+                return rhs.evaluate(program);
             }
-            Self::Unary { op, lhs } => {
-                lhs.translate(program)?;
+            Kind::Conversion(value) => {
+                let value = value.evaluate(program);
+                return program.convert(value, Some(self.datatype));
+            }
+            Kind::Unary { op, lhs } => {
+                let operand = lhs.evaluate(program);
                 match op {
                     UnaryOperator::Negation => {
-                        program.instruct(Operation::Neg, program.last(), Operand::None);
+                        program.instruct(Operation::Neg, operand, Operand::None);
+                    }
+                    UnaryOperator::Identity => return operand,
+                    UnaryOperator::Complement => {
+                        program.instruct(Operation::Mvn, operand, Operand::None);
                     }
                     UnaryOperator::Inversion => {
-                        program.instruct(
-                            Operation::Cmp,
-                            program.last(),
-                            Operand::Data(Data::Integer(0)),
-                        );
+                        program.instruct(Operation::Cmp, operand, Operand::Data(Data::Integer(0)));
                         program.instruct(Operation::CSet, Operand::Asm("eq"), Operand::None);
                         program.instruct(Operation::And, program.last(), BYTE);
                     }
                 }
             }
-            Self::Binary { op, lhs, rhs } => {
-                lhs.translate(program)?;
-                let operand1 = program.last();
-                rhs.translate(program)?;
-                let operand2 = program.last();
+            Kind::Binary {
+                op: op @ (BinaryOperator::And | BinaryOperator::Or),
+                lhs,
+                rhs,
+            } if !rhs.pure() => {
+                // The right side is only evaluated when the left one does not decide the result
+                let result = program.temporary(self.datatype);
+                let decided = program.generate_label("decided");
+                let end = program.generate_label("logical_end");
 
-                let upcast = max(operand1.datatype(program), operand2.datatype(program));
-                let operand1 = program.cast(operand1, upcast);
-                let operand2 = program.cast(operand2, upcast);
+                let operand1 = lhs.evaluate(program);
+                program.instruct(Operation::Cmp, operand1, ZERO);
+                program.instruct(
+                    match op {
+                        BinaryOperator::And => Operation::BEq,
+                        _ => Operation::BNe,
+                    },
+                    Operand::Label(decided.clone()),
+                    Operand::None,
+                );
 
-                match op {
-                    BinaryOperator::Addition => {
-                        program.instruct(Operation::Add, operand1, operand2)
-                    }
-                    BinaryOperator::Subtraction => {
-                        program.instruct(Operation::Sub, operand1, operand2)
-                    }
-                    BinaryOperator::Division => {
-                        program.instruct(Operation::Div, operand1, operand2)
-                    }
-                    BinaryOperator::Multiplication => {
-                        program.instruct(Operation::Mul, operand1, operand2)
-                    }
-                    BinaryOperator::BitwiseAnd => {
-                        program.instruct(Operation::And, operand1, operand2)
-                    }
-                    BinaryOperator::BitwiseOr => {
-                        program.instruct(Operation::Orr, operand1, operand2)
-                    }
-                    BinaryOperator::BitwiseXor => {
-                        program.instruct(Operation::Eor, operand1, operand2)
-                    }
-                    BinaryOperator::Remainder => {
-                        program.instruct(Operation::Div, operand1.clone(), operand2.clone());
-                        program.instruct(Operation::Mul, operand2, program.last());
-                        program.instruct(Operation::Sub, operand1, program.last());
-                    }
-                    BinaryOperator::LeftShift => {
-                        program.instruct(Operation::Lsl, operand1, operand2);
-                    }
-                    BinaryOperator::RightShift => {
-                        program.instruct(Operation::Asr, operand1, operand2);
-                    }
-                    BinaryOperator::Equal => {
-                        program.instruct(Operation::Cmp, operand1, operand2);
-                        program.instruct(Operation::CSet, Operand::Asm("eq"), Operand::None);
-                        program.instruct(Operation::And, program.last(), BYTE);
-                    }
-                    BinaryOperator::NotEqual => {
-                        program.instruct(Operation::Cmp, operand1, operand2);
-                        program.instruct(Operation::CSet, Operand::Asm("ne"), Operand::None);
-                        program.instruct(Operation::And, program.last(), BYTE);
-                    }
-                    BinaryOperator::Greater => {
-                        program.instruct(Operation::Cmp, operand1, operand2);
-                        program.instruct(Operation::CSet, Operand::Asm("gt"), Operand::None);
-                        program.instruct(Operation::And, program.last(), BYTE);
-                    }
-                    BinaryOperator::Less => {
-                        program.instruct(Operation::Cmp, operand1, operand2);
-                        program.instruct(Operation::CSet, Operand::Asm("lt"), Operand::None);
-                        program.instruct(Operation::And, program.last(), BYTE);
-                    }
-                    BinaryOperator::GreaterEqual => {
-                        program.instruct(Operation::Cmp, operand1, operand2);
-                        program.instruct(Operation::CSet, Operand::Asm("ge"), Operand::None);
-                        program.instruct(Operation::And, program.last(), BYTE);
-                    }
-                    BinaryOperator::LessEqual => {
-                        program.instruct(Operation::Cmp, operand1, operand2);
-                        program.instruct(Operation::CSet, Operand::Asm("le"), Operand::None);
-                        program.instruct(Operation::And, program.last(), BYTE);
-                    }
-                    BinaryOperator::And => {
-                        program.instruct(Operation::And, operand1, operand2);
-                        program.instruct(Operation::Cmp, program.last(), ZERO);
-                        program.instruct(Operation::CSet, Operand::Asm("ne"), Operand::None);
-                        program.instruct(Operation::And, program.last(), BYTE);
-                    }
-                    BinaryOperator::Or => {
-                        program.instruct(Operation::Orr, operand1, operand2);
-                        program.instruct(Operation::Cmp, program.last(), ZERO);
-                        program.instruct(Operation::CSet, Operand::Asm("ne"), Operand::None);
-                        program.instruct(Operation::And, program.last(), BYTE);
-                    }
-                }
+                let operand2 = rhs.evaluate(program);
+                let truth = binary(program, BinaryOperator::NotEqual, operand2, ZERO);
+                program.store(result.clone(), truth);
+                program.instruct(Operation::B, Operand::Label(end.clone()), Operand::None);
+
+                program.instruct(Operation::Lbl, Operand::Label(decided), Operand::None);
+                let value = Data::Integer((op == BinaryOperator::Or) as i32);
+                program.instruct(Operation::Mov, Operand::Temp, Operand::Data(value));
+                program.store(result.clone(), program.last());
+
+                program.instruct(Operation::Lbl, Operand::Label(end), Operand::None);
+                program.load(result);
+            }
+            Kind::Binary { op, lhs, rhs } => {
+                let operand1 = lhs.evaluate(program);
+                let operand2 = rhs.evaluate(program);
+                return binary(program, op, operand1, operand2);
             }
         };
-        Ok(())
+        program.last()
+    }
+}
+
+impl Locatable for Place {
+    fn locate(self, program: &mut Program) -> Operand {
+        let (symbol, index) = match self {
+            Place::Direct(symbol, offset) => return Operand::Identifier(symbol.identifier, offset),
+            Place::Indexed(symbol, index) => (symbol, index),
+        };
+
+        // Element address is computed as `base + (index << log2(size))`
+        let datatype = symbol.datatype.0;
+        let index = index.evaluate(program);
+        let shift = Operand::Data(Data::Long(datatype.size().trailing_zeros() as i64));
+        program.instruct(
+            if symbol.global {
+                Operation::Adg
+            } else {
+                Operation::Adr
+            },
+            Operand::Temp,
+            Operand::Identifier(symbol.identifier, 0),
+        );
+        let base = program.last();
+        program.instruct(Operation::Lsl, index, shift);
+        program.instruct(Operation::Add, base, program.last());
+
+        match program.last() {
+            Operand::Address(address) => Operand::Pointer(address, datatype),
+            _ => unreachable!(),
+        }
     }
 }
 
-impl<'a> Translatable<'a> for Vec<Statement<'a>> {
-    fn translate(self, program: &mut Program<'a>) -> Result<(), SemanticError<'a>> {
+impl<'a> Translatable for Vec<Statement<'a>> {
+    fn translate(self, program: &mut Program) {
+        // Instructions of the enclosing statement that follow the block are attributed to it again
+        let span = program.span;
         for statement in self {
-            statement.translate(program)?;
+            program.span = statement.span();
+            statement.translate(program);
+        }
+        program.span = span;
+    }
+}
+
+fn binary(
+    program: &mut Program,
+    op: BinaryOperator,
+    operand1: Operand,
+    operand2: Operand,
+) -> Operand {
+    // Operands are already converted to the same type by the checker
+    match op {
+        BinaryOperator::Addition => program.instruct(Operation::Add, operand1, operand2),
+        BinaryOperator::Subtraction => program.instruct(Operation::Sub, operand1, operand2),
+        BinaryOperator::Division => program.instruct(Operation::Div, operand1, operand2),
+        BinaryOperator::Multiplication => program.instruct(Operation::Mul, operand1, operand2),
+        BinaryOperator::BitwiseAnd => program.instruct(Operation::And, operand1, operand2),
+        BinaryOperator::BitwiseOr => program.instruct(Operation::Orr, operand1, operand2),
+        BinaryOperator::BitwiseXor => program.instruct(Operation::Eor, operand1, operand2),
+        BinaryOperator::Remainder => {
+            program.instruct(Operation::Div, operand1.clone(), operand2.clone());
+            program.instruct(Operation::Mul, operand2, program.last());
+            program.instruct(Operation::Sub, operand1, program.last());
+        }
+        BinaryOperator::LeftShift => {
+            program.instruct(Operation::Lsl, operand1, operand2);
+        }
+        BinaryOperator::RightShift => {
+            program.instruct(Operation::Asr, operand1, operand2);
+        }
+        BinaryOperator::Equal => {
+            program.instruct(Operation::Cmp, operand1, operand2);
+            program.instruct(Operation::CSet, Operand::Asm("eq"), Operand::None);
+            program.instruct(Operation::And, program.last(), BYTE);
+        }
+        BinaryOperator::NotEqual => {
+            program.instruct(Operation::Cmp, operand1, operand2);
+            program.instruct(Operation::CSet, Operand::Asm("ne"), Operand::None);
+            program.instruct(Operation::And, program.last(), BYTE);
+        }
+        BinaryOperator::Greater => {
+            program.instruct(Operation::Cmp, operand1, operand2);
+            program.instruct(Operation::CSet, Operand::Asm("gt"), Operand::None);
+            program.instruct(Operation::And, program.last(), BYTE);
+        }
+        BinaryOperator::Less => {
+            program.instruct(Operation::Cmp, operand1, operand2);
+            program.instruct(Operation::CSet, Operand::Asm("lt"), Operand::None);
+            program.instruct(Operation::And, program.last(), BYTE);
+        }
+        BinaryOperator::GreaterEqual => {
+            program.instruct(Operation::Cmp, operand1, operand2);
+            program.instruct(Operation::CSet, Operand::Asm("ge"), Operand::None);
+            program.instruct(Operation::And, program.last(), BYTE);
+        }
+        BinaryOperator::LessEqual => {
+            program.instruct(Operation::Cmp, operand1, operand2);
+            program.instruct(Operation::CSet, Operand::Asm("le"), Operand::None);
+            program.instruct(Operation::And, program.last(), BYTE);
+        }
+        // Operands are reduced to their truth values first, so that `1 && 2` is true
+        BinaryOperator::And => {
+            let operand1 = binary(program, BinaryOperator::NotEqual, operand1, ZERO);
+            let operand2 = binary(program, BinaryOperator::NotEqual, operand2, ZERO);
+            program.instruct(Operation::And, operand1, operand2);
+        }
+        BinaryOperator::Or => {
+            let operand1 = binary(program, BinaryOperator::NotEqual, operand1, ZERO);
+            let operand2 = binary(program, BinaryOperator::NotEqual, operand2, ZERO);
+            program.instruct(Operation::Orr, operand1, operand2);
         }
-        Ok(())
+    }
+
+    program.last()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::Data,
+        testing::{assemble, check},
+    };
+
+    /// Floats are tested with a float comparison, the integer one cannot take their registers
+    #[test]
+    fn float_conditions() {
+        for condition in ["f ? 1 : 2", "!f", "f && 1", "f || 0", "(f, f) ? g : 0"] {
+            let text =
+                format!("int main() {{ float f = 1.5; float g = 2.0; return {condition}; }}");
+            let assembly = assemble(&text);
+            assert!(assembly.contains("fcmp s"), "{condition}:\n{assembly}");
+            assert!(!assembly.contains("\tcmp s"), "{condition}:\n{assembly}");
+        }
+
+        let assembly =
+            assemble("int main() { float f = 1.5; for (int i = 0; f; f = f - 1.0) {} return 0; }");
+        assert!(assembly.contains("fcmp s"), "{assembly}");
+    }
+
+    /// The right side of a logical operator is skipped once the left one decides the result,
+    /// unless it is safe to evaluate either way
+    #[test]
+    fn logical_operators() {
+        for (op, branch) in [("&&", "b.eq Ldecided"), ("||", "b.ne Ldecided")] {
+            let text = format!("int main() {{ int a = 1; int b = 2; return a {op} b++; }}");
+            let assembly = assemble(&text);
+            let skip = assembly
+                .find(branch)
+                .unwrap_or_else(|| panic!("{op}:\n{assembly}"));
+            assert!(assembly[skip..].contains("add w"), "{op}:\n{assembly}");
+
+            let text = format!("int main() {{ int a = 1; int b = 2; return a {op} b; }}");
+            let assembly = assemble(&text);
+            assert!(!assembly.contains("decided"), "{op}:\n{assembly}");
+            assert_eq!(assembly.matches("cset").count(), 2, "{op}:\n{assembly}");
+        }
+    }
+
+    /// Chars are signed both when they are folded and when they are loaded at runtime
+    #[test]
+    fn signed_chars() {
+        let text = "const char c = '\\xff'; int folded = c; int main() { char local = c; return local + folded; }";
+        let (context, _) = check(text);
+        let folded = &context.globals[&context.local_name("folded")].1;
+        assert_eq!(folded, &[Data::Integer(-1)]);
+
+        let assembly = assemble(text);
+        assert!(assembly.contains("ldrsb w"), "{assembly}");
+        assert!(!assembly.contains("ldrb"), "{assembly}");
+    }
+
+    /// Cases in loops are dispatched to by their switch
+    #[test]
+    fn nested_cases() {
+        let assembly = assemble(
+            "int main() { int x = 2; int r = 0; switch (x) { case 1: for (int i = 0; i < 2; i++) { case 2: r++; } } return r; }",
+        );
+        assert_eq!(assembly.matches("b.eq Lcase_").count(), 2, "{assembly}");
+    }
+
+    /// Ranges too wide for a long fall back to comparisons instead of overflowing
+    #[test]
+    fn wide_switch() {
+        let assembly = assemble(
+            "int main() { long x = 0; switch (x) { case -9223372036854775807 - 1: case -1: case 0: case 9223372036854775807: return 1; } return 0; }",
+        );
+        assert!(!assembly.contains("jump_table"), "{assembly}");
     }
 }
@@ -1,89 +1,99 @@
 use super::intermediate::{Instruction, Operand, Operation};
 use crate::{
-    ast::{Compound, Data, Datatype, Primitive},
-    semantic::SemanticError,
+    ast::{Compound, Data, Primitive, Qualifiers, Storage},
+    Span,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Debug,
 };
-use std::{collections::HashMap, fmt::Debug};
 
-pub struct Program<'a> {
+pub struct Program {
     pub globals: HashMap<String, (Compound, Vec<Data>)>,
+    /// Read-only data, such as the constant globals and the string literals
+    pub constants: HashMap<String, (Compound, Vec<Data>)>,
+    /// Globals that are defined by another object
+    pub externals: HashMap<String, Compound>,
+    /// Globals that the other objects can refer to, by their names in C
+    pub exported: HashSet<String>,
     pub locals: HashMap<String, Compound>,
     pub instructions: Vec<Instruction>,
+    /// Span of the statement being translated, which new instructions are attributed to
+    pub span: Span,
 
-    types: HashMap<&'a str, Compound>,
-    scope: usize,
     label: usize,
+    breaks: Vec<String>,
+    /// Labels of the cases of the enclosing switches that are not placed yet
+    cases: Vec<VecDeque<String>>,
 }
 
-impl<'a> Program<'a> {
-    pub fn new() -> Self {
-        Program {
-            scope: 0,
+impl Program {
+    /// Program with the variables that are defined by the checker,
+    /// the globals are sorted out by their storage classes and qualifiers
+    pub fn new(
+        globals: HashMap<String, (Compound, Vec<Data>)>,
+        locals: HashMap<String, Compound>,
+        storage: HashMap<String, Storage>,
+        qualifiers: HashMap<String, Qualifiers>,
+    ) -> Self {
+        let mut program = Program {
             label: 0,
-            types: HashMap::new(),
+            breaks: Vec::new(),
+            cases: Vec::new(),
             instructions: Vec::new(),
-            locals: HashMap::new(),
+            span: Span::default(),
+            locals,
             globals: HashMap::new(),
-        }
-    }
-
-    pub fn toplevel(&self) -> bool {
-        self.scope == 0
-    }
-
-    pub fn push_scope(&mut self) -> () {
-        self.scope += 1;
-    }
+            constants: HashMap::new(),
+            externals: HashMap::new(),
+            exported: HashSet::new(),
+        };
 
-    pub fn pop_scope(&mut self) -> () {
-        self.scope -= 1;
+        for (identifier, (datatype, values)) in globals {
+            let storage = storage.get(&identifier).copied();
+            // Static ones keep their unique identifiers, which the assembler keeps to itself
+            if storage != Some(Storage::Static) {
+                program.exported.insert(identifier.clone());
+            }
+            match storage {
+                Some(Storage::Extern) => {
+                    program.externals.insert(identifier, datatype);
+                }
+                _ if qualifiers.get(&identifier).is_some_and(|x| x.constant) => {
+                    program.constants.insert(identifier, (datatype, values));
+                }
+                _ => {
+                    program.globals.insert(identifier, (datatype, values));
+                }
+            }
+        }
+        program
     }
 
-    fn infer_scope(&self, name: &'a str) -> Result<usize, SemanticError<'a>> {
-        (1..=self.scope)
-            .rev()
-            .find(|i| self.locals.contains_key(format!("{name}_{i}").as_str()))
-            .or_else(|| {
-                if self.globals.contains_key(format!("{name}_0").as_str()) {
-                    Some(0)
-                } else {
-                    None
-                }
-            })
-            .ok_or(SemanticError {
-                message: format!("'{}' is not defined!", name),
-                token: Some(name),
-            })
+    /// Makes `break` jump to the label until the matching `pop_break`
+    pub fn push_break(&mut self, label: String) {
+        self.breaks.push(label);
     }
 
-    pub fn infer_name(&self, name: &'a str) -> Result<String, SemanticError<'a>> {
-        let scope = self.infer_scope(name)?;
-        Ok(format!("{name}_{}", scope))
+    pub fn pop_break(&mut self) {
+        self.breaks.pop();
     }
 
-    pub fn is_global(&self, name: &'a str) -> Result<bool, SemanticError<'a>> {
-        Ok(self.infer_scope(name)? == 0)
+    pub fn break_label(&self) -> Option<String> {
+        self.breaks.last().cloned()
     }
 
-    fn is_defined_here(&mut self, name: &'a str) -> bool {
-        self.locals.contains_key(self.local_name(name).as_str())
+    /// Gives the labels to the switch's cases in order until the matching `pop_cases`
+    pub fn push_cases(&mut self, labels: Vec<String>) {
+        self.cases.push(labels.into());
     }
 
-    fn local_name(&self, name: &'a str) -> String {
-        format!("{name}_{}", self.scope)
+    pub fn pop_cases(&mut self) {
+        self.cases.pop();
     }
 
-    fn resolve_type(&self, datatype: Datatype<'a>) -> Result<Compound, SemanticError<'a>> {
-        match datatype {
-            Datatype::Type(datatype) => Ok(datatype),
-            Datatype::Alias(alias) => match self.types.get(alias) {
-                Some(datatype) => Ok(*datatype),
-                None => Err(SemanticError {
-                    message: format!("Type '{}' is not defined!", alias),
-                    token: Some(alias),
-                }),
-            },
-        }
+    pub fn next_case(&mut self) -> Option<String> {
+        self.cases.last_mut()?.pop_front()
     }
 
     pub fn generate_label(&mut self, prefix: &str) -> String {
@@ -92,44 +102,36 @@ impl<'a> Program<'a> {
         label
     }
 
-    pub fn type_of(&self, identifier: &'a str) -> Option<Primitive> {
-        self.locals
-            .get(identifier)
-            .or_else(|| self.globals.get(identifier).and_then(|x| Some(&x.0)))
-            .and_then(|x| Some(x.0))
+    /// Allocates an unnamed stack slot, its name cannot clash with any C identifier
+    pub fn temporary(&mut self, datatype: Primitive) -> Operand {
+        let name = self.generate_label("$tmp");
+        self.locals.insert(name.clone(), Compound(datatype, 1));
+        Operand::Identifier(name, 0)
     }
 
-    pub fn define_variable(
-        &mut self,
-        name: &'a str,
-        datatype: Datatype<'a>,
-        value: Vec<Data>,
-    ) -> Result<(), SemanticError<'a>> {
-        if self.is_defined_here(name) {
-            return Err(SemanticError {
-                message: format!("Variable '{}' is already defined in this scope!", name),
-                token: Some(name),
-            });
-        }
-
-        let datatype = self.resolve_type(datatype)?;
-        if self.toplevel() {
-            self.globals
-                .insert(self.local_name(name), (datatype, value));
-        } else {
-            self.locals.insert(self.local_name(name), datatype);
-        }
+    pub fn type_of(&self, identifier: &str) -> Option<Primitive> {
+        self.compound_of(identifier).map(|x| x.0)
+    }
 
-        Ok(())
+    pub fn compound_of(&self, identifier: &str) -> Option<Compound> {
+        self.locals
+            .get(identifier)
+            .or_else(|| self.globals.get(identifier).map(|x| &x.0))
+            .or_else(|| self.constants.get(identifier).map(|x| &x.0))
+            .or_else(|| self.externals.get(identifier))
+            .copied()
     }
 
-    pub fn define_type(
+    /// Stores read-only data under a generated name that cannot clash with any C identifier
+    pub fn define_constant(
         &mut self,
-        name: &'a str,
-        datatype: Datatype<'a>,
-    ) -> Result<(), SemanticError<'a>> {
-        self.types.insert(name, self.resolve_type(datatype)?);
-        Ok(())
+        prefix: &str,
+        datatype: Compound,
+        value: Vec<Data>,
+    ) -> String {
+        let name = self.generate_label(prefix);
+        self.constants.insert(name.clone(), (datatype, value));
+        name
     }
 
     pub fn last(&self) -> Operand {
@@ -141,6 +143,7 @@ impl<'a> Program<'a> {
             operation,
             operand1,
             operand2,
+            span: self.span,
         });
     }
 
@@ -154,7 +157,8 @@ impl<'a> Program<'a> {
         size + (16 - size % 16)
     }
 
-    pub fn cast(&mut self, operand: Operand, to: Option<Primitive>) -> Operand {
+    /// Explicit conversion, such as the one of a cast expression
+    pub fn convert(&mut self, operand: Operand, to: Option<Primitive>) -> Operand {
         let from = operand.datatype(self);
         let cast = match (from, to) {
             (from, to) if from == to => None,
@@ -166,24 +170,69 @@ impl<'a> Program<'a> {
                 Some(Primitive::Float),
                 Some(Primitive::Byte | Primitive::Short | Primitive::Int | Primitive::Long),
             ) => Some(Operation::FCvtZS),
+            (Some(Primitive::Byte | Primitive::Short | Primitive::Int), Some(Primitive::Long)) => {
+                Some(Operation::SxtW)
+            }
+            (Some(Primitive::Long), Some(Primitive::Byte | Primitive::Short | Primitive::Int)) => {
+                Some(Operation::Narrow)
+            }
+            (Some(Primitive::Short | Primitive::Int), Some(Primitive::Byte)) => {
+                Some(Operation::SxtB)
+            }
+            (Some(Primitive::Int), Some(Primitive::Short)) => Some(Operation::SxtH),
             _ => None,
         };
 
         if let Some(instruction) = cast {
             self.instruct(instruction, operand, Operand::None);
-            self.last()
+            // Conversions between floats and longs or narrow integers go through an int
+            self.convert(self.last(), to)
         } else {
             operand
         }
     }
+
+    fn is_global_location(&self, location: &Operand) -> bool {
+        match location {
+            Operand::Identifier(identifier, _) => {
+                self.globals.contains_key(identifier)
+                    || self.constants.contains_key(identifier)
+                    || self.externals.contains_key(identifier)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn load(&mut self, location: Operand) -> Operand {
+        let operation = if self.is_global_location(&location) {
+            Operation::Ldg
+        } else {
+            Operation::Ldr
+        };
+
+        self.instruct(operation, Operand::Temp, location);
+        self.last()
+    }
+
+    /// Stores the value, which is already converted to the type of the location
+    pub fn store(&mut self, location: Operand, value: Operand) -> Operand {
+        let operation = if self.is_global_location(&location) {
+            Operation::Stg
+        } else {
+            Operation::Str
+        };
+
+        self.instruct(operation, location, value.clone());
+        value
+    }
 }
 
-impl<'a> Debug for Program<'a> {
+impl Debug for Program {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "globals:\n")?;
-        for (key, (_, values)) in self.globals.iter() {
+        writeln!(f, "globals:")?;
+        for (key, (_, values)) in self.globals.iter().chain(self.constants.iter()) {
             let representation: Vec<_> = values
-                .into_iter()
+                .iter()
                 .map(|value| match value {
                     Data::Float(x) => format!("{x:e}"),
                     x => format!("{x}"),
@@ -196,11 +245,11 @@ impl<'a> Debug for Program<'a> {
                 &format!("[{}]", representation.join(", "))
             };
 
-            write!(f, "  {key} = {representation}\n")?
+            writeln!(f, "  {key} = {representation}")?
         }
         write!(f, "\nmain:\n")?;
         for (i, instruction) in self.instructions.iter().enumerate() {
-            write!(f, "{:3}) {:?}\n", i, instruction)?;
+            writeln!(f, "{:3}) {:?}", i, instruction)?;
         }
         Ok(())
     }
@@ -1,7 +1,8 @@
+use crate::{error::syntax::SyntaxError, Span};
 use std::cmp::Ordering;
 use std::fmt::Display;
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub enum Primitive {
     Int,
     Float,
@@ -10,10 +11,10 @@ pub enum Primitive {
     Byte,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Compound(pub Primitive, pub usize);
 
-impl<'a> Ord for Primitive {
+impl Ord for Primitive {
     fn cmp(&self, other: &Self) -> Ordering {
         let hierarchy = [
             Primitive::Byte,
@@ -29,6 +30,12 @@ impl<'a> Ord for Primitive {
     }
 }
 
+impl PartialOrd for Primitive {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Primitive {
     pub fn size(&self) -> usize {
         match self {
@@ -41,10 +48,21 @@ impl Primitive {
     }
 
     pub fn floating(&self) -> bool {
-        match self {
-            Self::Float => true,
-            _ => false,
-        }
+        matches!(self, Self::Float)
+    }
+}
+
+/// Names of the types in C
+impl Display for Primitive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Long => "long",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Short => "short",
+            Self::Byte => "char",
+        };
+        write!(f, "{}", name)
     }
 }
 
@@ -54,17 +72,98 @@ impl Compound {
     }
 }
 
+/// Type qualifiers, the ones of an alias add up with the ones it is used with
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Qualifiers {
+    /// Whether the variable cannot be assigned to after its initialization
+    pub constant: bool,
+    /// Whether every access is kept as written, as the value can change outside the program
+    pub volatile: bool,
+}
+
+impl Qualifiers {
+    pub fn union(self, other: Qualifiers) -> Qualifiers {
+        Qualifiers {
+            constant: self.constant || other.constant,
+            volatile: self.volatile || other.volatile,
+        }
+    }
+}
+
+/// Storage class of a variable, variables without one are on the stack inside of functions
+/// and visible to the other objects on the top-level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Storage {
+    /// Placed into data even inside of functions, and hidden from the other objects
+    Static,
+    /// Defined by another object, unless it is defined later on
+    Extern,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Datatype<'a> {
-    Type(Compound),
-    Alias(&'a str),
+    Type(Compound, Qualifiers),
+    Alias(&'a str, Span, Qualifiers),
+}
+
+impl<'a> Datatype<'a> {
+    pub fn qualify(self, qualifiers: Qualifiers) -> Self {
+        match self {
+            Datatype::Type(compound, x) => Datatype::Type(compound, x.union(qualifiers)),
+            Datatype::Alias(name, span, x) => Datatype::Alias(name, span, x.union(qualifiers)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Operator {
-    Binary(BinaryOperator),
+pub enum Prefix {
     Unary(UnaryOperator),
-    Group,
+    Update(UpdateOperator),
+    Dereference,
+    Reference,
+}
+
+impl Prefix {
+    pub fn precedence(&self) -> i32 {
+        2
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Postfix {
+    Update(UpdateOperator),
+    Index,
+    Call,
+    Member,
+}
+
+impl Postfix {
+    pub fn precedence(&self) -> i32 {
+        1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Infix {
+    Binary(BinaryOperator),
+    Assignment(Option<BinaryOperator>),
+    Conditional,
+    Comma,
+}
+
+impl Infix {
+    pub fn precedence(&self) -> i32 {
+        match *self {
+            Infix::Binary(x) => x.precedence(),
+            Infix::Conditional => 13,
+            Infix::Assignment(_) => 14,
+            Infix::Comma => 15,
+        }
+    }
+
+    pub fn right_associative(&self) -> bool {
+        matches!(self, Infix::Assignment(_) | Infix::Conditional)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -114,10 +213,45 @@ impl BinaryOperator {
     }
 }
 
+/// Symbols of the operators in C
+impl Display for BinaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Self::Addition => "+",
+            Self::Subtraction => "-",
+            Self::Division => "/",
+            Self::Multiplication => "*",
+            Self::Remainder => "%",
+            Self::Greater => ">",
+            Self::Less => "<",
+            Self::GreaterEqual => ">=",
+            Self::LessEqual => "<=",
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::Or => "||",
+            Self::And => "&&",
+            Self::BitwiseAnd => "&",
+            Self::BitwiseOr => "|",
+            Self::BitwiseXor => "^",
+            Self::LeftShift => "<<",
+            Self::RightShift => ">>",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UnaryOperator {
     Negation,
+    Identity,
     Inversion,
+    Complement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpdateOperator {
+    Increment,
+    Decrement,
 }
 
 #[derive(Debug, Display, Clone, Copy, PartialEq)]
@@ -129,6 +263,36 @@ pub enum Data {
     Byte(i8),
 }
 
+impl Data {
+    pub fn zero(datatype: Primitive) -> Self {
+        match datatype {
+            Primitive::Long => Data::Long(0),
+            Primitive::Int => Data::Integer(0),
+            Primitive::Float => Data::Float(0.0),
+            Primitive::Short => Data::Short(0),
+            Primitive::Byte => Data::Byte(0),
+        }
+    }
+
+    /// Whether all bits of the value are zero, which negative zero is not
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Data::Float(x) => x.to_bits() == 0,
+            x => i64::from(x) == 0,
+        }
+    }
+
+    pub fn datatype(&self) -> Primitive {
+        match self {
+            Data::Long(_) => Primitive::Long,
+            Data::Integer(_) => Primitive::Int,
+            Data::Float(_) => Primitive::Float,
+            Data::Short(_) => Primitive::Short,
+            Data::Byte(_) => Primitive::Byte,
+        }
+    }
+}
+
 impl From<&Data> for f32 {
     fn from(value: &Data) -> Self {
         match *value {
@@ -156,48 +320,152 @@ impl From<&Data> for i64 {
 #[derive(Debug)]
 pub enum Value<'a> {
     Data(Data),
-    Pointer(&'a str, usize),
+    Pointer(Lvalue<'a>),
+    /// Bytes of a string literal without its terminator
+    String(Vec<u8>),
+}
+
+/// Lvalues are spanned by their names
+#[derive(Debug)]
+pub enum Lvalue<'a> {
+    Variable(&'a str, Span),
+    Element(&'a str, Box<Expression<'a>>, Span),
+}
+
+impl<'a> Lvalue<'a> {
+    pub fn span(&self) -> Span {
+        match self {
+            Lvalue::Variable(_, span) | Lvalue::Element(.., span) => *span,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum Expression<'a> {
-    Value(Value<'a>),
+    Value(Value<'a>, Span),
     Binary {
         op: BinaryOperator,
         lhs: Box<Expression<'a>>,
         rhs: Box<Expression<'a>>,
+        span: Span,
     },
     Unary {
         op: UnaryOperator,
         lhs: Box<Expression<'a>>,
+        span: Span,
+    },
+    Assignment {
+        op: Option<BinaryOperator>,
+        target: Lvalue<'a>,
+        value: Box<Expression<'a>>,
+        span: Span,
+    },
+    Update {
+        op: UpdateOperator,
+        target: Lvalue<'a>,
+        postfix: bool,
+        span: Span,
+    },
+    Conditional {
+        condition: Box<Expression<'a>>,
+        then: Box<Expression<'a>>,
+        otherwise: Box<Expression<'a>>,
+        span: Span,
     },
+    /// Evaluates both operands from left to right, the value is the one of the right operand
+    Comma {
+        lhs: Box<Expression<'a>>,
+        rhs: Box<Expression<'a>>,
+        span: Span,
+    },
+    Cast {
+        datatype: Datatype<'a>,
+        value: Box<Expression<'a>>,
+        span: Span,
+    },
+    Sizeof(Sizeof<'a>, Span),
+}
+
+#[derive(Debug)]
+pub enum Sizeof<'a> {
+    Type(Datatype<'a>),
+    /// The expression is only inspected and never evaluated
+    Expression(Box<Expression<'a>>),
+}
+
+impl<'a> Expression<'a> {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Value(_, span)
+            | Expression::Binary { span, .. }
+            | Expression::Unary { span, .. }
+            | Expression::Assignment { span, .. }
+            | Expression::Update { span, .. }
+            | Expression::Conditional { span, .. }
+            | Expression::Comma { span, .. }
+            | Expression::Cast { span, .. }
+            | Expression::Sizeof(_, span) => *span,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Loop<'a> {
-    pub initialization: Variable<'a>,
+    /// Declaration of the loop's variables, an expression or nothing
+    pub initialization: Box<Statement<'a>>,
     pub condition: Expression<'a>,
-    pub increment: Assignment<'a>,
+    pub increment: Expression<'a>,
     pub body: Vec<Statement<'a>>,
 }
 
+#[derive(Debug)]
+pub struct Switch<'a> {
+    pub value: Expression<'a>,
+    pub body: Vec<Statement<'a>>,
+}
+
+#[derive(Debug)]
+pub struct Case<'a> {
+    /// Missing for the `default` label
+    pub value: Option<Expression<'a>>,
+}
+
 #[derive(Debug)]
 pub struct Variable<'a> {
     pub datatype: Datatype<'a>,
+    /// Present when the variable is an array of the type
+    pub extent: Option<Extent<'a>>,
+    pub storage: Option<Storage>,
     pub name: &'a str,
+    /// Span of the name
+    pub span: Span,
     pub assignment: Option<Assignment<'a>>,
 }
 
+/// Size of an array, which is left to be inferred from the initializer when omitted
+#[derive(Debug)]
+pub enum Extent<'a> {
+    Inferred,
+    Size(Expression<'a>),
+}
+
 #[derive(Debug)]
 pub struct Assignment<'a> {
-    pub identifier: (&'a str, usize),
     pub value: Initializer<'a>,
 }
 
 #[derive(Debug)]
 pub enum Initializer<'a> {
     Expression(Expression<'a>),
-    List(Vec<Expression<'a>>),
+    List(Vec<Entry<'a>>),
+}
+
+/// Value of an initialization list, which is placed at its designated index
+/// or right after the previous one
+#[derive(Debug)]
+pub struct Entry<'a> {
+    pub index: Option<Expression<'a>>,
+    pub value: Expression<'a>,
 }
 
 #[derive(Debug)]
@@ -205,22 +473,37 @@ pub struct Function<'a> {
     #[allow(dead_code)] // FUTURE: do something with function type
     pub datatype: Datatype<'a>,
     pub name: &'a str,
+    /// Span of the name
+    pub span: Span,
     pub body: Vec<Statement<'a>>,
 }
 
 #[derive(Debug)]
 pub struct Type<'a> {
     pub datatype: Datatype<'a>,
+    pub extent: Option<Extent<'a>>,
     pub name: &'a str,
+    /// Span of the name
+    pub span: Span,
 }
 
+/// Statements are spanned from their first token to their last one
 #[derive(Debug)]
+#[allow(dead_code)] // Declarations leave nothing to translate, so only the debug output shows their spans
 pub enum Statement<'a> {
-    Assignment(Assignment<'a>),
-    Variable(Variable<'a>),
-    Function(Function<'a>),
-    Type(Type<'a>),
-    Loop(Loop<'a>),
-    Return(Expression<'a>),
-    Noop,
+    Expression(Expression<'a>, Span),
+    /// Variables of a single declaration, such as `int a = 1, b[2];`
+    Variables(Vec<Variable<'a>>, Span),
+    Function(Function<'a>, Span),
+    Type(Type<'a>, Span),
+    Loop(Loop<'a>, Span),
+    Switch(Switch<'a>, Span),
+    Case(Case<'a>, Span),
+    Break(Span),
+    Label(&'a str, Span),
+    Goto(&'a str, Span),
+    Return(Expression<'a>, Span),
+    Noop(Span),
+    /// A statement that failed to parse, spanned up to where the parsing recovered
+    Error(SyntaxError<'a>, Span),
 }
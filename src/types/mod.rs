@@ -1,12 +1,70 @@
 pub mod ast;
+pub mod typed;
+
+use std::{
+    cmp::{max, min},
+    fmt::Debug,
+};
+
+/// Range of bytes in one of the source files, macro expansions span their invocations
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub file: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Span covering both of the spans, when the other one is not in the same file it is left out
+    pub fn to(self, other: Span) -> Span {
+        if self.file != other.file {
+            return self;
+        }
+
+        Span {
+            file: self.file,
+            start: min(self.start, other.start),
+            end: max(self.end, other.end),
+        }
+    }
+}
+
+impl Debug for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}..{}", self.file, self.start, self.end)
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Token<'a> {
-    Data(Literal, &'a str),
-    Identifier(&'a str),
-    Keyword(&'a str),
-    Symbol(&'a str),
-    Comment,
+    Data(Literal, &'a str, Span),
+    Identifier(&'a str, Span),
+    /// Identifier declared by a `typedef`, only told apart by the analyzer
+    Typename(&'a str, Span),
+    Keyword(&'a str, Span),
+    Symbol(&'a str, Span),
+}
+
+impl<'a> Token<'a> {
+    pub fn text(&self) -> &'a str {
+        match *self {
+            Token::Data(_, x, _)
+            | Token::Identifier(x, _)
+            | Token::Typename(x, _)
+            | Token::Keyword(x, _)
+            | Token::Symbol(x, _) => x,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match *self {
+            Token::Data(_, _, x)
+            | Token::Identifier(_, x)
+            | Token::Typename(_, x)
+            | Token::Keyword(_, x)
+            | Token::Symbol(_, x) => x,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -15,6 +73,8 @@ pub enum Literal {
     Long(i64),
     Floating(f32),
     Character(char),
+    /// Quoted text with its escape sequences still in place
+    String,
 }
 
 pub trait TokenStream<'a>: Iterator<Item = Token<'a>> {}
@@ -0,0 +1,164 @@
+use crate::{
+    ast::{BinaryOperator, Compound, Data, Primitive, Qualifiers, UnaryOperator, UpdateOperator},
+    Span,
+};
+
+/// Variable resolved to its unique identifier
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub identifier: String,
+    pub datatype: Compound,
+    /// Whether the variable is in data, such as the top-level and the static ones
+    pub global: bool,
+    pub qualifiers: Qualifiers,
+}
+
+/// Resolved location of an lvalue
+#[derive(Debug)]
+pub enum Place {
+    /// Variable or its element at an offset that is known ahead of time
+    Direct(Symbol, usize),
+    /// Element at an index that is computed at runtime, the index is always a long
+    Indexed(Symbol, Box<Expression>),
+}
+
+impl Place {
+    pub fn symbol(&self) -> &Symbol {
+        match self {
+            Place::Direct(symbol, _) | Place::Indexed(symbol, _) => symbol,
+        }
+    }
+
+    pub fn datatype(&self) -> Primitive {
+        self.symbol().datatype.0
+    }
+}
+
+/// Expression along with the type of its value, operands are already converted
+/// to the types their operators work with
+#[derive(Debug)]
+pub struct Expression {
+    pub kind: Kind,
+    pub datatype: Primitive,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum Kind {
+    Data(Data),
+    /// Address of the string's bytes along with their terminator
+    String(Vec<u8>),
+    Load(Place),
+    Binary {
+        op: BinaryOperator,
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+    },
+    Unary {
+        op: UnaryOperator,
+        lhs: Box<Expression>,
+    },
+    /// Compound assignments are done in the type of the value,
+    /// then the result is converted to the type of the target
+    Assignment {
+        op: Option<BinaryOperator>,
+        target: Place,
+        value: Box<Expression>,
+    },
+    Update {
+        op: UpdateOperator,
+        target: Place,
+        postfix: bool,
+    },
+    Conditional {
+        condition: Box<Expression>,
+        then: Box<Expression>,
+        otherwise: Box<Expression>,
+    },
+    /// Value of the right operand, after the left one is evaluated for its side effects
+    Comma {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+    },
+    /// Conversion of the value to the type of the expression
+    Conversion(Box<Expression>),
+}
+
+impl Expression {
+    /// Whether the expression can be evaluated speculatively,
+    /// i.e. it has no side effects and none of its loads can fault
+    pub fn pure(&self) -> bool {
+        match &self.kind {
+            Kind::Data(_) | Kind::String(_) => true,
+            // Volatile variables are only accessed where the program does
+            Kind::Load(place) if place.symbol().qualifiers.volatile => false,
+            Kind::Load(Place::Direct(..)) => true,
+            Kind::Load(Place::Indexed(..)) => false,
+            Kind::Binary { lhs, rhs, .. } => lhs.pure() && rhs.pure(),
+            Kind::Unary { lhs, .. } => lhs.pure(),
+            Kind::Assignment { .. } | Kind::Update { .. } => false,
+            Kind::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => condition.pure() && then.pure() && otherwise.pure(),
+            Kind::Comma { lhs, rhs } => lhs.pure() && rhs.pure(),
+            Kind::Conversion(value) => value.pure(),
+        }
+    }
+}
+
+/// Initial values of a local variable, which are stored element by element
+#[derive(Debug)]
+pub struct Initialization {
+    pub symbol: Symbol,
+    pub values: Vec<Expression>,
+}
+
+#[derive(Debug)]
+pub struct Loop<'a> {
+    pub initialization: Option<Box<Statement<'a>>>,
+    pub condition: Expression,
+    pub increment: Expression,
+    pub body: Vec<Statement<'a>>,
+}
+
+/// Switch whose value is either an int or a long, the cases are of the same type
+#[derive(Debug)]
+pub struct Switch<'a> {
+    pub value: Expression,
+    pub body: Vec<Statement<'a>>,
+}
+
+/// Statements that are left to translate, declarations are already resolved by the checker
+#[derive(Debug)]
+pub enum Statement<'a> {
+    Expression(Expression, Span),
+    Initializations(Vec<Initialization>, Span),
+    Function(Vec<Statement<'a>>, Span),
+    Loop(Loop<'a>, Span),
+    Switch(Switch<'a>, Span),
+    /// Value of the case, missing for the `default` label
+    Case(Option<i64>, Span),
+    Break(Span),
+    Label(&'a str, Span),
+    Goto(&'a str, Span),
+    Return(Expression, Span),
+}
+
+impl<'a> Statement<'a> {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Expression(_, span)
+            | Statement::Initializations(_, span)
+            | Statement::Function(_, span)
+            | Statement::Loop(_, span)
+            | Statement::Switch(_, span)
+            | Statement::Case(_, span)
+            | Statement::Break(span)
+            | Statement::Label(_, span)
+            | Statement::Goto(_, span)
+            | Statement::Return(_, span) => *span,
+        }
+    }
+}